@@ -0,0 +1,6 @@
+pub mod metrics;
+pub mod modem;
+pub mod protobuf;
+pub mod server;
+
+pub use modem::{ApiError, Credentials, Modem, ModemError, SessionExpired};
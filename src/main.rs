@@ -1,201 +1,225 @@
-use std::fmt::Error;
-use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
-
-use anyhow::{anyhow, Context, Result};
-use prometheus_client::collector::Collector;
-use prometheus_client::encoding::{DescriptorEncoder, EncodeLabelSet, EncodeLabelValue};
-use prometheus_client::encoding::text::encode;
-use prometheus_client::metrics::MetricType;
-use prometheus_client::registry::{Registry, Unit};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use serde::de::DeserializeOwned;
-use warp::Filter;
-
-#[derive(Debug, Serialize)]
-#[serde(rename = "request")]
-struct ModemRequest<T>(T);
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "lowercase")]
-enum ModemResponse<T = ()> {
-    Response(T),
-    Error {
-        code: i32,
-        message: String,
-    },
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use modem_exporter::{Credentials, Modem, ModemError};
+use modem_exporter::metrics::{check_collectors, gather_metrics, ScrapeMetrics, StatisticsCache, COLLECTORS, DEFAULT_METRIC_PREFIX};
+use modem_exporter::modem::DEFAULT_ACCEPT_LANGUAGE;
+use modem_exporter::server::{self, Exporter, IpNetwork, ListenAddress, ModemConfig, DEFAULT_METRICS_PATH};
+use tracing::warn;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Prometheus exporter for Huawei modems")]
+struct Args {
+    #[arg(long, env = "MODEM_ADDRESS", default_value = "http://192.168.8.1")]
+    address: String,
+    #[arg(long, env = "LISTEN_ADDRESS", default_value = "0.0.0.0:9091", value_parser = ListenAddress::from_str)]
+    listen: ListenAddress,
+    #[arg(long, env = "METRICS_PATH", default_value = DEFAULT_METRICS_PATH, value_parser = parse_metrics_path)]
+    metrics_path: String,
+    #[arg(long, env = "MODEM_TIMEOUT_SECS", default_value = "10", value_parser = parse_secs)]
+    timeout: Duration,
+    #[arg(long, env = "SCRAPE_DEADLINE_SECS", default_value = "9", value_parser = parse_secs)]
+    scrape_deadline: Duration,
+    #[arg(long, env = "MODEM_USER_AGENT")]
+    user_agent: Option<String>,
+    #[arg(long, env = "MODEM_ACCEPT_LANGUAGE", default_value = DEFAULT_ACCEPT_LANGUAGE, help = "Language requested for operator names and status strings; not all firmware honors it")]
+    accept_language: String,
+    #[arg(long, env = "MODEM_TRAFFIC_PATH")]
+    traffic_path: Option<String>,
+    #[arg(long, env = "SIGNAL_SAMPLE_INTERVAL", value_parser = parse_secs)]
+    signal_sample_interval: Option<Duration>,
+    #[arg(long, env = "MODEM_USERNAME", default_value = "admin")]
+    username: String,
+    #[arg(long, env = "MODEM_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+    #[arg(long, env = "MODEM_PASSWORD_FILE")]
+    password_file: Option<PathBuf>,
+    #[arg(long, env = "RUST_LOG", default_value = "info")]
+    log_level: String,
+    #[arg(long, env = "MODEM_TARGETS", value_delimiter = ',', help = "Targets allowed in ?target= that also receive the modem credentials; otherwise targets on the allowed networks are scraped anonymously")]
+    targets: Option<Vec<String>>,
+    #[arg(long, env = "MODEM_ALLOWED_NETWORKS", value_delimiter = ',', value_parser = IpNetwork::from_str)]
+    allowed_networks: Option<Vec<IpNetwork>>,
+    #[arg(long, env = "MODEM_RETRIES", default_value_t = 2)]
+    retries: u32,
+    #[arg(long, env = "MODEM_CACHE_TTL_SECS")]
+    cache_ttl: Option<u64>,
+    #[arg(long, env = "MODEM_INSECURE_TLS", default_value = "false", value_parser = parse_bool)]
+    insecure_tls: bool,
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+    #[arg(long, env = "METRICS_USERNAME", requires = "metrics_password")]
+    metrics_username: Option<String>,
+    #[arg(long, env = "METRICS_PASSWORD", hide_env_values = true, requires = "metrics_username")]
+    metrics_password: Option<String>,
+    #[arg(long, env = "MODEM_COLLECTORS", value_delimiter = ',', value_parser = parse_collector)]
+    collectors: Option<Vec<String>>,
+    #[arg(long, env = "MODEM_REDACT_IDENTITY", default_value = "false", value_parser = parse_bool)]
+    redact_identity: bool,
+    #[arg(long, env = "METRIC_PREFIX", default_value = DEFAULT_METRIC_PREFIX, value_parser = parse_metric_prefix)]
+    metric_prefix: String,
+    #[arg(long, env = "INSTANCE_LABEL", value_parser = parse_label_name, help = "Label carrying the modem host on every metric")]
+    instance_label: Option<String>,
+    #[arg(long, env = "INSTANCE_ALIAS", requires = "instance_label", help = "Instance label value for the default modem instead of its host")]
+    instance_alias: Option<String>,
+    #[arg(long, env = "STATE_FILE", help = "File persisting lifetime traffic totals across modem counter resets")]
+    state_file: Option<PathBuf>,
+    #[arg(long, env = "DEBUG_ENDPOINTS", default_value = "false", value_parser = parse_bool)]
+    debug_endpoints: bool,
+    #[arg(long, help = "Print the metrics once and exit")]
+    once: bool,
+    #[arg(long, conflicts_with = "once", help = "Check the modem session, login and collectors, then exit")]
+    check: bool,
 }
 
-impl<T> ModemResponse<T> {
-    fn ok(self) -> Result<T> {
-        match self {
-            ModemResponse::Response(val) => Ok(val),
-            ModemResponse::Error { code, message } =>
-                Err(anyhow!("api error: code={code} message={message}"))
-        }
-    }
+fn parse_metrics_path(path: &str) -> Result<String> {
+    let valid = path.starts_with('/')
+        && path.trim_matches('/').split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+    anyhow::ensure!(valid, "invalid metrics path: {path:?}");
+    Ok(path.to_string())
 }
 
-#[derive(Debug, Deserialize)]
-struct SessionResponse {
-    #[serde(rename = "SesInfo")]
-    session: String,
-    #[serde(rename = "TokInfo")]
-    token: String,
+fn parse_secs(secs: &str) -> Result<Duration> {
+    secs.parse().ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .with_context(|| format!("invalid duration: {secs:?}"))
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "PascalCase")]
-struct TrafficStatistics {
-    current_upload: u64,
-    current_download: u64,
-    current_connect_time: u64,
-    total_upload: u64,
-    total_download: u64,
-    total_connect_time: u64,
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "" | "0" | "false" | "no" | "off" => Ok(false),
+        _ => anyhow::bail!("invalid boolean: {value:?}"),
+    }
 }
 
-impl Collector for TrafficStatistics {
-    #[allow(non_camel_case_types)]
-    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
-        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
-        enum period {
-            session,
-            total,
-        }
-        use period::*;
-
-        {
-            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
-            enum direction {
-                upload,
-                download,
-            }
-            use direction::*;
-
-            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
-            struct labels {
-                period: period,
-                direction: direction,
-            }
-
-            let mut transferred = encoder.encode_descriptor(
-                "modem_transferred", "Transferred bytes",
-                Some(&Unit::Bytes), MetricType::Gauge,
-            )?;
-
-            transferred.encode_family(&labels {
-                period: session,
-                direction: upload,
-            })?.encode_counter::<(), _, u64>(&self.current_upload, None)?;
-            transferred.encode_family(&labels {
-                period: session,
-                direction: download,
-            })?.encode_counter::<(), _, u64>(&self.current_download, None)?;
-
-            transferred.encode_family(&labels {
-                period: total,
-                direction: upload,
-            })?.encode_counter::<(), _, u64>(&self.total_upload, None)?;
-            transferred.encode_family(&labels {
-                period: total,
-                direction: download,
-            })?.encode_counter::<(), _, u64>(&self.total_download, None)?;
-        }
-
-        {
-            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
-            struct labels {
-                period: period,
-            }
-
-            let mut duration = encoder.encode_descriptor(
-                "modem_connect_duration", "Connected duration",
-                Some(&Unit::Seconds), MetricType::Counter,
-            )?;
-
-            duration.encode_family(&labels { period: session })?
-                .encode_counter::<(), _, u64>(&self.current_connect_time, None)?;
-            duration.encode_family(&labels { period: total })?
-                .encode_counter::<(), _, u64>(&self.total_connect_time, None)?;
-        }
-
-        Ok(())
-    }
+fn parse_collector(collector: &str) -> Result<String> {
+    let collector = collector.trim();
+    anyhow::ensure!(
+        COLLECTORS.contains(&collector),
+        "unknown collector: {collector:?}, valid collectors: {}", COLLECTORS.join(", "),
+    );
+    Ok(collector.to_string())
 }
 
-pub struct Modem {
-    client: Client,
-    session: Option<SessionResponse>,
+fn parse_metric_prefix(prefix: &str) -> Result<String> {
+    let valid = !prefix.is_empty() && prefix.chars().enumerate()
+        .all(|(i, c)| c == '_' || c == ':' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()));
+    anyhow::ensure!(valid, "invalid metric prefix: {prefix:?}");
+    Ok(prefix.to_string())
 }
 
-impl Modem {
-    pub fn new() -> Result<Modem> {
-        Ok(Self {
-            client: Client::new(),
-            session: None,
-        })
-    }
+fn parse_label_name(name: &str) -> Result<String> {
+    let valid = name.chars().enumerate()
+        .all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()));
+    anyhow::ensure!(valid, "invalid label name: {name:?}");
+    Ok(name.to_string())
+}
 
-    async fn get<Resp: DeserializeOwned>(&self, path: &str) -> Result<Resp> {
-        let mut builder = self.client.get(format!("http://192.168.8.1{path}"));
-        if let Some(session) = &self.session {
-            builder = builder.header("Cookie", &session.session)
-                .header("__RequestVerificationToken", &session.token);
+async fn check(modem: &Modem, login: bool) -> bool {
+    let report = |step: &str, result: Result<(), ModemError>| match result {
+        Ok(()) => {
+            println!("{step}: ok");
+            true
         }
-        let resp = builder.send().await?.error_for_status()?;
-        let data = resp.bytes().await?;
-        Ok(quick_xml::de::from_reader(data.as_ref())?)
-    }
-
-    async fn post<Req: Serialize, Resp: DeserializeOwned>(&self, path: &str, req: Req) -> Result<Resp> {
-        let mut builder = self.client.post(format!("http://192.168.8.1{path}"));
-        if let Some(session) = &self.session {
-            builder = builder.header("Cookie", &session.session)
-                .header("__RequestVerificationToken", &session.token);
+        Err(err) => {
+            println!("{step}: failed: {err:#}");
+            false
         }
-        let resp = builder.body(quick_xml::se::to_string(&req).context("serialize body")?)
-            .send().await?
-            .error_for_status()?;
-        let data = resp.text().await?;
-        Ok(quick_xml::de::from_reader(data.as_bytes()).context("deserialize response")?)
-    }
+    };
 
-    async fn gather_statistics(&mut self) -> Result<TrafficStatistics> {
-        self.session = self.get("/api/webserver/SesTokInfo").await.context("get session")?;
-
-        let traffic_stats = self.get::<ModemResponse<TrafficStatistics>>("/api/monitoring/traffic-statistics")
-            .await?
-            .ok()?;
-        Ok(traffic_stats)
+    if !report(if login { "session and login" } else { "session" }, modem.check().await) {
+        return false;
+    }
+    match check_collectors(modem).await {
+        Ok(collectors) => {
+            for (collector, ok) in &collectors {
+                println!("collector {collector}: {}", if *ok { "ok" } else { "unavailable" });
+            }
+            collectors.iter().any(|(_, ok)| *ok)
+        }
+        Err(err) => report("collectors", Err(err)),
     }
-}
-
-async fn gather_metrics() -> Result<String> {
-    let mut modem = Modem::new()?;
-    let stats = modem.gather_statistics().await?;
-
-    let mut registry = Registry::default();
-    registry.register_collector(Box::new(stats));
-
-    let mut data = String::new();
-    encode(&mut data, &registry).context("failed to encode")?;
-    Ok(data)
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
-    let metrics_route = warp::path!("metrics")
-        .then(move || {
-            async {
-                gather_metrics().await.unwrap_or_else(|err| format!("{err:?}"))
-            }
+    let args = Args::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_new(&args.log_level)
+            .with_context(|| format!("invalid log level: {:?}", args.log_level))?)
+        .with_writer(std::io::stderr)
+        .init();
+
+    let password = match &args.password_file {
+        Some(path) => Some(std::fs::read_to_string(path)
+            .with_context(|| format!("read password file {}", path.display()))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string()),
+        None => args.password,
+    };
+    let credentials = password
+        .filter(|password| !password.is_empty())
+        .map(|password| Credentials {
+            username: args.username,
+            password,
         });
+    let metrics_credentials = args.metrics_username
+        .zip(args.metrics_password)
+        .map(|(username, password)| Credentials { username, password });
+    let allowed_targets = args.targets
+        .map(|targets| targets.into_iter().map(|target| target.trim().to_string()).collect());
+    let config = ModemConfig {
+        credentials,
+        timeout: args.timeout,
+        insecure_tls: args.insecure_tls,
+        retries: args.retries,
+        cache_ttl: args.cache_ttl.map(Duration::from_secs),
+        collectors: args.collectors,
+        redact_identity: args.redact_identity,
+        metric_prefix: args.metric_prefix,
+        instance_label: args.instance_label,
+        instance_alias: args.instance_alias,
+        scrape_deadline: Some(args.scrape_deadline).filter(|deadline| !deadline.is_zero()),
+        state_file: args.state_file,
+        user_agent: args.user_agent.filter(|user_agent| !user_agent.is_empty()),
+        accept_language: Some(args.accept_language).filter(|language| !language.is_empty()),
+        traffic_path: args.traffic_path.filter(|path| !path.is_empty()),
+    };
+
+    if args.once {
+        let modem = config.build(args.address)?;
+        let scrape = ScrapeMetrics::new();
+        let output = gather_metrics(&modem, &scrape, &StatisticsCache::new(None)).await?;
+        print!("{output}");
+        if let Err(err) = modem.logout().await {
+            warn!("logout failed: {err:#}");
+        }
+        anyhow::ensure!(scrape.succeeded(), "scrape failed");
+        return Ok(());
+    }
 
-    warp::serve(metrics_route)
-        .run(SocketAddr::from_str("0.0.0.0:9091").unwrap())
-        .await;
+    if args.check {
+        let modem = config.build(args.address)?;
+        let ok = check(&modem, config.credentials.is_some()).await;
+        if let Err(err) = modem.logout().await {
+            warn!("logout failed: {err:#}");
+        }
+        anyhow::ensure!(ok, "check failed");
+        return Ok(());
+    }
 
-    Ok(())
+    let exporter = Exporter::new(args.address, config, allowed_targets)?
+        .with_allowed_networks(args.allowed_networks)
+        .with_admin_token(args.admin_token.filter(|token| !token.is_empty()))
+        .with_metrics_credentials(metrics_credentials)
+        .with_debug_endpoints(args.debug_endpoints)
+        .with_metrics_path(args.metrics_path)
+        .with_signal_sample_interval(args.signal_sample_interval.filter(|interval| !interval.is_zero()));
+    server::run(exporter, args.listen).await
 }
@@ -0,0 +1,1361 @@
+use std::collections::BTreeMap;
+use std::fmt::{Error, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use prometheus_client::collector::Collector;
+use prometheus_client::encoding::{DescriptorEncoder, EncodeLabelSet, EncodeLabelValue, LabelValueEncoder};
+use prometheus_client::encoding::text::encode;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::{ConstGauge, Gauge};
+use prometheus_client::metrics::MetricType;
+use prometheus_client::registry::{Registry, Unit};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::modem::{
+    Antenna, AvailableNetworks, CheckNotifications, ConvergedStatus, CurrentPlmn, DataSwitch, DeviceInformation, FirmwareUpdate, LifetimeTotals,
+    Modem, ModemError, MonitoringStatus, MonthStatistics, NetMode, PinStatus, RatSignal, SignalInfo, SignalSamples, SmsCount,
+    StartDate, Statistics, TrafficStatistics, WanInfo, WlanBasicSettings, WlanHostList,
+};
+
+impl Collector for TrafficStatistics {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+            enum direction {
+                upload,
+                download,
+            }
+            use direction::*;
+
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                direction: direction,
+            }
+
+            let mut session_transferred = encoder.encode_descriptor(
+                "session_transferred", "Transferred bytes in the current session",
+                Some(&Unit::Bytes), MetricType::Gauge,
+            )?;
+
+            session_transferred.encode_family(&labels { direction: upload })?
+                .encode_gauge(&(self.current_upload as f64))?;
+            session_transferred.encode_family(&labels { direction: download })?
+                .encode_gauge(&(self.current_download as f64))?;
+
+            let mut transferred = encoder.encode_descriptor(
+                "transferred", "Transferred bytes",
+                Some(&Unit::Bytes), MetricType::Counter,
+            )?;
+
+            transferred.encode_family(&labels { direction: upload })?
+                .encode_counter::<(), _, u64>(&self.total_upload, None)?;
+            transferred.encode_family(&labels { direction: download })?
+                .encode_counter::<(), _, u64>(&self.total_download, None)?;
+        }
+
+        encoder.encode_descriptor(
+            "session_connect_duration", "Connected duration of the current session",
+            Some(&Unit::Seconds), MetricType::Gauge,
+        )?.encode_gauge(&(self.current_connect_time as i64))?;
+
+        let established = SystemTime::now().duration_since(UNIX_EPOCH).ok()
+            .filter(|_| self.current_connect_time > 0)
+            .and_then(|now| now.as_secs().checked_sub(self.current_connect_time));
+        if let Some(established) = established {
+            encoder.encode_descriptor(
+                "connection_established_timestamp", "Time the current connection was established",
+                Some(&Unit::Seconds), MetricType::Gauge,
+            )?.encode_gauge(&(established.min(i64::MAX as u64) as i64))?;
+        }
+
+        encoder.encode_descriptor(
+            "connect_duration", "Connected duration",
+            Some(&Unit::Seconds), MetricType::Counter,
+        )?.encode_counter::<(), _, u64>(&self.total_connect_time, None)?;
+
+        let bytes_per_second = Unit::Other("bytes_per_second".to_string());
+        let rates = [
+            ("current_upload_rate", "Current upload throughput", self.current_upload_rate),
+            ("current_download_rate", "Current download throughput", self.current_download_rate),
+        ];
+        for (name, help, rate) in rates {
+            if let Some(rate) = rate {
+                encoder.encode_descriptor(name, help, Some(&bytes_per_second), MetricType::Gauge)?
+                    .encode_gauge(&(rate.min(i64::MAX as u64) as i64))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for LifetimeTotals {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+        enum direction {
+            upload,
+            download,
+        }
+        use direction::*;
+
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            direction: direction,
+        }
+
+        let mut transferred = encoder.encode_descriptor(
+            "lifetime_transferred", "Transferred bytes accumulated across modem counter resets",
+            Some(&Unit::Bytes), MetricType::Counter,
+        )?;
+
+        transferred.encode_family(&labels { direction: upload })?
+            .encode_counter::<(), _, u64>(&self.upload, None)?;
+        transferred.encode_family(&labels { direction: download })?
+            .encode_counter::<(), _, u64>(&self.download, None)?;
+
+        Ok(())
+    }
+}
+
+pub fn parse_signal_value(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() || value == "--" || value.eq_ignore_ascii_case("null") {
+        return None;
+    }
+
+    let value = [">=", "<=", ">", "<"].iter()
+        .find_map(|comparator| value.strip_prefix(comparator))
+        .unwrap_or(value);
+    let lower = value.to_ascii_lowercase();
+    let value = ["dbm", "db", "mhz"].iter()
+        .find_map(|unit| lower.strip_suffix(unit))
+        .unwrap_or(&lower);
+
+    value.trim().parse().ok().filter(|value: &f64| value.is_finite())
+}
+
+// LTE thresholds, from excellent to fair: RSRP >= -80, -90, -100 dBm and SINR >= 20, 13, 0 dB.
+fn connection_quality(rsrp: Option<f64>, sinr: Option<f64>) -> Option<u32> {
+    let level = |value: f64, thresholds: [f64; 3]| thresholds.iter().filter(|threshold| value >= **threshold).count() as u32;
+    let rsrp = rsrp.map(|rsrp| level(rsrp, [-100.0, -90.0, -80.0]));
+    let sinr = sinr.map(|sinr| level(sinr, [0.0, 13.0, 20.0]));
+    rsrp.into_iter().chain(sinr).min()
+}
+
+fn connection_quality_name(quality: u32) -> &'static str {
+    match quality {
+        3 => "excellent",
+        2 => "good",
+        1 => "fair",
+        _ => "poor",
+    }
+}
+
+fn parse_temperature(value: &str) -> Option<f64> {
+    let value = value.trim()
+        .trim_end_matches(['C', 'c', '℃'])
+        .trim_end_matches('°')
+        .trim();
+    let value = value.parse::<f64>().ok().filter(|value| value.is_finite())?;
+    Some(if value.abs() > 150.0 { value / 10.0 } else { value })
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct LabelValue(String);
+
+impl From<&str> for LabelValue {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl EncodeLabelValue for LabelValue {
+    fn encode(&self, encoder: &mut LabelValueEncoder) -> std::result::Result<(), Error> {
+        for c in self.0.chars() {
+            match c {
+                '\\' => encoder.write_str("\\\\")?,
+                '"' => encoder.write_str("\\\"")?,
+                '\n' => encoder.write_str("\\n")?,
+                c => encoder.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_cell_id(value: &str) -> Option<u64> {
+    let value = value.trim();
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None if value.chars().all(|c| c.is_ascii_digit()) => value.parse().ok(),
+        None => u64::from_str_radix(value, 16).ok(),
+    }
+}
+
+fn parse_number<T: FromStr>(value: &Option<String>) -> Option<T> {
+    value.as_deref()?.trim().parse().ok()
+}
+
+impl Collector for SignalInfo {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        let dbm = Unit::Other("dbm".to_string());
+        let db = Unit::Other("db".to_string());
+        let gauges = [
+            ("signal_rssi", "Received signal strength indicator", &dbm, &self.rssi),
+            ("signal_rsrp", "Reference signal received power", &dbm, &self.rsrp),
+            ("signal_rsrq", "Reference signal received quality", &db, &self.rsrq),
+            ("signal_sinr", "Signal to interference plus noise ratio", &db, &self.sinr),
+            ("signal_ecio", "Energy per chip to interference ratio", &db, &self.ecio),
+        ];
+
+        for (name, help, unit, value) in gauges {
+            if let Some(value) = value.as_deref().and_then(parse_signal_value) {
+                encoder.encode_descriptor(name, help, Some(unit), MetricType::Gauge)?
+                    .encode_gauge(&value)?;
+            }
+        }
+
+        let rsrp = self.rsrp.as_deref().and_then(parse_signal_value);
+        let sinr = self.sinr.as_deref().and_then(parse_signal_value);
+        if let Some(quality) = connection_quality(rsrp, sinr) {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                quality: &'static str,
+            }
+
+            encoder.encode_descriptor(
+                "connection_quality", "Connection quality from poor (0) to excellent (3)",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&quality)?;
+
+            encoder.encode_descriptor(
+                "connection_quality_info", "Connection quality classification",
+                None, MetricType::Gauge,
+            )?.encode_family(&labels { quality: connection_quality_name(quality) })?.encode_gauge(&1i64)?;
+        }
+
+        if let Some(band) = self.band.as_deref().map(str::trim).filter(|band| !band.is_empty()) {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                band: LabelValue,
+            }
+
+            encoder.encode_descriptor(
+                "lte_band_info", "Active LTE band",
+                None, MetricType::Gauge,
+            )?.encode_family(&labels { band: band.into() })?.encode_gauge(&1i64)?;
+        }
+
+        {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+            enum direction {
+                upload,
+                download,
+            }
+            use direction::*;
+
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                direction: direction,
+            }
+
+            let bandwidths = [(upload, &self.ulbandwidth), (download, &self.dlbandwidth)]
+                .into_iter()
+                .filter_map(|(direction, value)| Some((direction, value.as_deref().and_then(parse_signal_value)?)))
+                .collect::<Vec<_>>();
+            if !bandwidths.is_empty() {
+                let mhz = Unit::Other("mhz".to_string());
+                let mut bandwidth = encoder.encode_descriptor(
+                    "lte_bandwidth", "LTE channel bandwidth",
+                    Some(&mhz), MetricType::Gauge,
+                )?;
+                for (direction, value) in bandwidths {
+                    bandwidth.encode_family(&labels { direction })?.encode_gauge(&value)?;
+                }
+            }
+        }
+
+        if let Some(temperature) = self.temperature.as_deref().and_then(parse_temperature) {
+            encoder.encode_descriptor(
+                "temperature", "Device temperature",
+                Some(&Unit::Celsius), MetricType::Gauge,
+            )?.encode_gauge(&temperature)?;
+        }
+
+        if let Some(cell_id) = self.cell_id.as_deref().and_then(parse_cell_id) {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                cell_id: LabelValue,
+                enb_id: LabelValue,
+                sector_id: LabelValue,
+                tac: Option<LabelValue>,
+                pci: Option<LabelValue>,
+            }
+
+            let label = |value: &Option<String>| {
+                value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(LabelValue::from)
+            };
+            encoder.encode_descriptor(
+                "cell_info", "Serving cell identity",
+                None, MetricType::Gauge,
+            )?.encode_family(&labels {
+                cell_id: cell_id.to_string().as_str().into(),
+                enb_id: (cell_id >> 8).to_string().as_str().into(),
+                sector_id: (cell_id & 0xff).to_string().as_str().into(),
+                tac: label(&self.tac).or_else(|| label(&self.lac)),
+                pci: label(&self.pci),
+            })?.encode_gauge(&1i64)?;
+        }
+
+        if let Some(carriers) = parse_number::<u32>(&self.ca) {
+            encoder.encode_descriptor(
+                "lte_aggregated_carriers", "Number of aggregated LTE carriers",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&carriers)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for SignalSamples {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        let dbm = Unit::Other("dbm".to_string());
+        let gauges = [
+            ("signal_rsrp_min", "Minimum sampled reference signal received power since the last scrape", self.min),
+            ("signal_rsrp_max", "Maximum sampled reference signal received power since the last scrape", self.max),
+            ("signal_rsrp_avg", "Average sampled reference signal received power since the last scrape", self.avg()),
+        ];
+
+        for (name, help, value) in gauges {
+            encoder.encode_descriptor(name, help, Some(&dbm), MetricType::Gauge)?
+                .encode_gauge(&value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for DeviceInformation {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            device_name: LabelValue,
+            serial_number: LabelValue,
+            imei: LabelValue,
+            imsi: LabelValue,
+            hardware_version: LabelValue,
+            software_version: LabelValue,
+            webui_version: LabelValue,
+        }
+
+        encoder.encode_descriptor(
+            "device_info", "Device information",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            device_name: self.device_name.as_str().into(),
+            serial_number: self.serial_number.as_str().into(),
+            imei: self.imei.as_str().into(),
+            imsi: self.imsi.as_str().into(),
+            hardware_version: self.hardware_version.as_str().into(),
+            software_version: self.software_version.as_str().into(),
+            webui_version: self.webui_version.as_str().into(),
+        })?.encode_gauge(&1i64)?;
+
+        if let Some(uptime) = self.uptime.as_deref().and_then(|uptime| uptime.trim().parse::<u64>().ok()) {
+            encoder.encode_descriptor(
+                "uptime", "Device uptime",
+                Some(&Unit::Seconds), MetricType::Counter,
+            )?.encode_counter::<(), _, u64>(&uptime, None)?;
+        }
+
+        let sim = [("iccid", &self.iccid), ("msisdn", &self.msisdn)]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value.as_deref()?.trim())))
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name, LabelValue::from(value)))
+            .collect::<Vec<_>>();
+        if !sim.is_empty() {
+            encoder.encode_descriptor(
+                "sim_info", "SIM card identity",
+                None, MetricType::Gauge,
+            )?.encode_family(&sim)?.encode_gauge(&1i64)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for SmsCount {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+        enum storage {
+            local,
+            sim,
+        }
+        use storage::*;
+
+        {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+            enum folder {
+                inbox,
+                outbox,
+                draft,
+            }
+            use folder::*;
+
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                storage: storage,
+                folder: folder,
+            }
+
+            let mut messages = encoder.encode_descriptor(
+                "sms_messages", "Stored SMS messages",
+                None, MetricType::Gauge,
+            )?;
+
+            for (storage, folder, value) in [
+                (local, inbox, self.local_inbox),
+                (local, outbox, self.local_outbox),
+                (local, draft, self.local_draft),
+                (sim, inbox, self.sim_inbox),
+                (sim, outbox, self.sim_outbox),
+                (sim, draft, self.sim_draft),
+            ] {
+                messages.encode_family(&labels { storage, folder })?.encode_gauge(&value)?;
+            }
+        }
+
+        {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                storage: storage,
+            }
+
+            let mut unread = encoder.encode_descriptor(
+                "sms_unread", "Unread SMS messages",
+                None, MetricType::Gauge,
+            )?;
+
+            unread.encode_family(&labels { storage: local })?.encode_gauge(&self.local_unread)?;
+            unread.encode_family(&labels { storage: sim })?.encode_gauge(&self.sim_unread)?;
+        }
+
+        Ok(())
+    }
+}
+
+const CONNECTION_STATUS_CONNECTED: u32 = 901;
+
+fn is_connection_failure(code: u32) -> bool {
+    matches!(
+        code,
+        // connection failed, the profile is invalid
+        2 | 3 | 5 | 8 | 20 | 21 | 23 | 27..=33
+        // network access not allowed
+        | 7 | 11 | 14 | 37
+        // connection failed, roaming not allowed
+        | 12 | 13
+        // automatic dial-up failed or no network available
+        | 112..=115
+        // connection failed, bandwidth exceeded
+        | 201
+        // connection failed, connection error
+        | 904 | 906
+    )
+}
+
+fn network_type_name(code: u32) -> &'static str {
+    match code {
+        0 => "no_service",
+        1 => "GSM",
+        2 => "GPRS",
+        3 => "EDGE",
+        4 | 41 => "WCDMA",
+        5 | 42 => "HSDPA",
+        6 | 43 => "HSUPA",
+        7 | 44 => "HSPA",
+        8 | 61 => "TD-SCDMA",
+        9 | 45 => "HSPA+",
+        17 => "HSPA+ 64QAM",
+        18 => "HSPA+ MIMO",
+        46 => "DC-HSPA+",
+        62 => "TD-HSDPA",
+        63 => "TD-HSUPA",
+        64 => "TD-HSPA",
+        65 => "TD-HSPA+",
+        19 | 101 => "LTE",
+        1011 => "LTE CA",
+        111 => "NR",
+        _ => "unknown",
+    }
+}
+
+impl Collector for MonitoringStatus {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        encoder.encode_descriptor(
+            "connection_status", "Whether the modem is connected",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&((self.connection_status == CONNECTION_STATUS_CONNECTED) as u32))?;
+
+        encoder.encode_descriptor(
+            "connection_status_code", "Raw connection status code reported by the modem",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&self.connection_status)?;
+
+        encoder.encode_descriptor(
+            "connection_failed", "Whether the last connection attempt failed",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&(is_connection_failure(self.connection_status) as u32))?;
+
+        encoder.encode_descriptor(
+            "roaming", "Whether the modem is roaming",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&((self.roaming_status != 0) as u32))?;
+
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            network_type: &'static str,
+        }
+
+        encoder.encode_descriptor(
+            "network_type_info", "Current radio access technology",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            network_type: network_type_name(self.current_network_type),
+        })?.encode_gauge(&1i64)?;
+
+        if let Some(level) = parse_number::<i64>(&self.signal_icon).or_else(|| parse_number(&self.signal_strength)) {
+            encoder.encode_descriptor(
+                "signal_level", "Signal strength in bars (0-5)",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&level.clamp(0, 5))?;
+        }
+
+        if let Some(percent) = parse_number::<u32>(&self.battery_percent) {
+            encoder.encode_descriptor(
+                "battery_percent", "Battery charge level",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&percent)?;
+        }
+
+        if let Some(status) = parse_number::<i64>(&self.battery_status) {
+            encoder.encode_descriptor(
+                "battery_charging", "Whether the battery is charging",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&((status == 1) as i64))?;
+        }
+
+        if let Some(throttled) = parse_number::<i64>(&self.thermal_protection) {
+            encoder.encode_descriptor(
+                "thermal_throttled", "Whether thermal protection is throttling the modem",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&((throttled != 0) as i64))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for ConvergedStatus {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        encoder.encode_descriptor(
+            "5g_connected", "Whether the modem is connected to a 5G cell",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&((parse_number::<i64>(&self.nr_connected) == Some(1)) as i64))?;
+
+        encoder.encode_descriptor(
+            "nsa_mode", "Whether 5G runs in non-standalone mode",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&((parse_number::<i64>(&self.nsa_mode) == Some(1)) as i64))?;
+
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+        enum rat {
+            lte,
+            nr,
+        }
+
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            rat: rat,
+        }
+
+        let signals = [(rat::lte, &self.lte), (rat::nr, &self.nr)];
+        let dbm = Unit::Other("dbm".to_string());
+        let db = Unit::Other("db".to_string());
+        let gauges = [
+            ("rat_signal_rssi", "Received signal strength indicator per radio access technology", &dbm,
+                (|signal| &signal.rssi) as fn(&RatSignal) -> &Option<String>),
+            ("rat_signal_rsrp", "Reference signal received power per radio access technology", &dbm,
+                |signal| &signal.rsrp),
+            ("rat_signal_rsrq", "Reference signal received quality per radio access technology", &db,
+                |signal| &signal.rsrq),
+            ("rat_signal_sinr", "Signal to interference plus noise ratio per radio access technology", &db,
+                |signal| &signal.sinr),
+        ];
+
+        for (name, help, unit, field) in gauges {
+            let values = signals.iter()
+                .filter_map(|(rat, signal)| {
+                    Some((rat.clone(), field(signal.as_ref()?).as_deref().and_then(parse_signal_value)?))
+                })
+                .collect::<Vec<_>>();
+            if values.is_empty() {
+                continue;
+            }
+            let mut gauge = encoder.encode_descriptor(name, help, Some(unit), MetricType::Gauge)?;
+            for (rat, value) in values {
+                gauge.encode_family(&labels { rat })?.encode_gauge(&value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for MonthStatistics {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+            enum direction {
+                upload,
+                download,
+            }
+            use direction::*;
+
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                direction: direction,
+            }
+
+            let mut transferred = encoder.encode_descriptor(
+                "month_transferred", "Transferred bytes in the current month",
+                Some(&Unit::Bytes), MetricType::Gauge,
+            )?;
+
+            transferred.encode_family(&labels { direction: upload })?
+                .encode_gauge(&(self.current_month_upload as f64))?;
+            transferred.encode_family(&labels { direction: download })?
+                .encode_gauge(&(self.current_month_download as f64))?;
+        }
+
+        encoder.encode_descriptor(
+            "month_duration", "Connected duration in the current month",
+            Some(&Unit::Seconds), MetricType::Gauge,
+        )?.encode_gauge(&(self.month_duration as i64))?;
+
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            last_clear_time: LabelValue,
+        }
+
+        encoder.encode_descriptor(
+            "month_last_clear_info", "Date the monthly statistics were last cleared",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            last_clear_time: self.month_last_clear_time.as_str().into(),
+        })?.encode_gauge(&1i64)?;
+
+        Ok(())
+    }
+}
+
+fn sim_state_name(code: u32) -> &'static str {
+    match code {
+        255 => "absent",
+        256 => "failed",
+        257..=259 => "ready",
+        260 => "pin_required",
+        261 => "puk_required",
+        _ => "unknown",
+    }
+}
+
+impl Collector for PinStatus {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            state: &'static str,
+        }
+
+        let current = sim_state_name(self.sim_state);
+        let mut state = encoder.encode_descriptor(
+            "sim_state", "Current SIM card state",
+            None, MetricType::Gauge,
+        )?;
+        for name in ["ready", "pin_required", "puk_required", "absent", "failed", "unknown"] {
+            state.encode_family(&labels { state: name })?.encode_gauge(&((name == current) as i64))?;
+        }
+
+        if let Some(remaining_attempts) = self.remaining_attempts {
+            encoder.encode_descriptor(
+                "sim_pin_remaining_attempts", "Remaining SIM PIN attempts",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&remaining_attempts)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for WlanHostList {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            band: LabelValue,
+        }
+
+        let mut clients = BTreeMap::<&str, u32>::new();
+        for host in &self.hosts.host {
+            let band = match host.frequency.trim() {
+                "" => "unknown",
+                band => band,
+            };
+            *clients.entry(band).or_default() += 1;
+        }
+
+        let mut encoder = encoder.encode_descriptor(
+            "wlan_clients", "Connected WLAN clients",
+            None, MetricType::Gauge,
+        )?;
+        for (band, count) in clients {
+            encoder.encode_family(&labels { band: band.into() })?.encode_gauge(&count)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for WlanBasicSettings {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            ssid: LabelValue,
+            channel: LabelValue,
+        }
+
+        encoder.encode_descriptor(
+            "wlan_info", "WLAN settings",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            ssid: self.wifi_ssid.as_str().into(),
+            channel: self.wifi_channel.as_str().into(),
+        })?.encode_gauge(&1i64)?;
+
+        Ok(())
+    }
+}
+
+impl Collector for DataSwitch {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        encoder.encode_descriptor(
+            "mobile_data_enabled", "Whether mobile data is enabled",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&((self.dataswitch == 1) as i64))?;
+
+        Ok(())
+    }
+}
+
+fn antenna_type_name(code: Option<u32>) -> &'static str {
+    match code {
+        Some(0) => "internal",
+        Some(1) => "external",
+        Some(2) => "auto",
+        _ => "unknown",
+    }
+}
+
+impl Collector for Antenna {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        let antenna_type = parse_number::<u32>(&self.antennatype);
+
+        if let Some(antenna_type) = antenna_type {
+            encoder.encode_descriptor(
+                "antenna_external", "Whether the external antenna is selected",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&((antenna_type == 1) as i64))?;
+        }
+
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            mode: &'static str,
+            settings: Option<LabelValue>,
+        }
+
+        encoder.encode_descriptor(
+            "antenna_info", "Antenna mode",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            mode: antenna_type_name(antenna_type),
+            settings: self.antenna_settings.as_deref().map(str::trim).filter(|settings| !settings.is_empty()).map(Into::into),
+        })?.encode_gauge(&1i64)?;
+
+        Ok(())
+    }
+}
+
+fn plmn_state_name(code: Option<u32>) -> &'static str {
+    match code {
+        Some(1) => "available",
+        Some(2) => "registered",
+        Some(3) => "forbidden",
+        _ => "unknown",
+    }
+}
+
+fn plmn_rat_name(code: Option<u32>) -> &'static str {
+    match code {
+        Some(0) => "GSM",
+        Some(2) => "WCDMA",
+        Some(7) => "LTE",
+        Some(11 | 12) => "NR",
+        _ => "unknown",
+    }
+}
+
+impl Collector for CurrentPlmn {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            name: LabelValue,
+            short_name: LabelValue,
+            numeric: LabelValue,
+            state: &'static str,
+            rat: &'static str,
+        }
+
+        encoder.encode_descriptor(
+            "operator_info", "Current network operator",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            name: self.full_name.as_str().into(),
+            short_name: self.short_name.as_str().into(),
+            numeric: self.numeric.as_str().into(),
+            state: plmn_state_name(parse_number(&self.state)),
+            rat: plmn_rat_name(parse_number(&self.rat)),
+        })?.encode_gauge(&1i64)?;
+
+        Ok(())
+    }
+}
+
+impl Collector for AvailableNetworks {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            name: LabelValue,
+            numeric: LabelValue,
+            state: &'static str,
+            rat: &'static str,
+        }
+
+        let mut networks = encoder.encode_descriptor(
+            "available_network_info", "Network operators found by the last scan",
+            None, MetricType::Gauge,
+        )?;
+        for network in &self.network {
+            networks.encode_family(&labels {
+                name: network.full_name.as_str().into(),
+                numeric: network.numeric.as_str().into(),
+                state: plmn_state_name(parse_number(&network.state)),
+                rat: plmn_rat_name(parse_number(&network.rat)),
+            })?.encode_gauge(&1i64)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for CheckNotifications {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        if let Some(full) = self.sms_storage_full {
+            encoder.encode_descriptor(
+                "sms_storage_full", "Whether the SMS storage is full",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&((full == 1) as i64))?;
+        }
+
+        if let Some(unread) = self.unread_message {
+            encoder.encode_descriptor(
+                "unread_notifications", "Unread notifications",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&unread)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for FirmwareUpdate {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        let version = self.new_version.trim();
+
+        encoder.encode_descriptor(
+            "firmware_update_available", "Whether a firmware update is available",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&(!version.is_empty() as i64))?;
+
+        if !version.is_empty() {
+            #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+            struct labels {
+                version: LabelValue,
+            }
+
+            encoder.encode_descriptor(
+                "firmware_update_info", "Available firmware update version",
+                None, MetricType::Gauge,
+            )?.encode_family(&labels { version: version.into() })?.encode_gauge(&1i64)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_data_limit(limit: &str) -> Option<u64> {
+    let limit = limit.trim().to_ascii_uppercase();
+    let (number, multiplier) = [("TB", 1u64 << 40), ("GB", 1 << 30), ("MB", 1 << 20), ("KB", 1 << 10), ("B", 1)]
+        .into_iter()
+        .find_map(|(suffix, multiplier)| Some((limit.strip_suffix(suffix)?, multiplier)))
+        .unwrap_or((&limit, 1));
+    let bytes = (number.trim().parse::<f64>().ok()? * multiplier as f64) as u64;
+    (bytes > 0).then_some(bytes)
+}
+
+impl Collector for StartDate {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        if let Some(limit) = self.data_limit.as_deref().and_then(parse_data_limit) {
+            encoder.encode_descriptor(
+                "data_limit", "Configured monthly data limit",
+                Some(&Unit::Bytes), MetricType::Gauge,
+            )?.encode_gauge(&(limit as i64))?;
+        }
+
+        if let Some(day) = self.start_day {
+            encoder.encode_descriptor(
+                "billing_cycle_start_day", "Day of month the billing cycle starts",
+                None, MetricType::Gauge,
+            )?.encode_gauge(&day)?;
+        }
+
+        Ok(())
+    }
+}
+
+const NETWORK_MODE_LTE_ONLY: &str = "03";
+
+impl Collector for NetMode {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            mode: LabelValue,
+            band: LabelValue,
+            lte_band: LabelValue,
+        }
+
+        encoder.encode_descriptor(
+            "net_mode_info", "Configured network mode and band masks",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            mode: self.network_mode.as_str().into(),
+            band: self.network_band.as_str().into(),
+            lte_band: self.lte_band.as_str().into(),
+        })?.encode_gauge(&1i64)?;
+
+        encoder.encode_descriptor(
+            "network_mode_locked_lte", "Whether the network mode is locked to LTE only",
+            None, MetricType::Gauge,
+        )?.encode_gauge(&((self.network_mode.trim() == NETWORK_MODE_LTE_ONLY) as i64))?;
+
+        Ok(())
+    }
+}
+
+impl Collector for WanInfo {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        let wan = [
+            ("ip", &self.wan_ip_address),
+            ("primary_dns", &self.primary_dns),
+            ("secondary_dns", &self.secondary_dns),
+            ("apn", &self.apn),
+        ]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value.as_deref()?.trim())))
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name, LabelValue::from(value)))
+            .collect::<Vec<_>>();
+        if !wan.is_empty() {
+            encoder.encode_descriptor(
+                "wan_info", "WAN address, DNS servers and APN",
+                None, MetricType::Gauge,
+            )?.encode_family(&wan)?.encode_gauge(&1i64)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum error_kind {
+    session,
+    traffic,
+    decode,
+    network,
+    timeout,
+}
+
+impl error_kind {
+    fn classify(err: &ModemError) -> error_kind {
+        match err {
+            ModemError::Network(_) => error_kind::network,
+            ModemError::Decode(_) => error_kind::decode,
+            ModemError::Timeout => error_kind::timeout,
+            ModemError::Auth(_) | ModemError::SessionExpired => error_kind::session,
+            err if err.is_session_expired() => error_kind::session,
+            ModemError::ApiError { .. } | ModemError::Other(_) => error_kind::traffic,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ErrorLabels {
+    kind: error_kind,
+}
+
+#[derive(Default)]
+pub struct ScrapeMetrics {
+    success: Gauge,
+    ever_succeeded: AtomicBool,
+    duration: Gauge<f64, AtomicU64>,
+    errors: Family<ErrorLabels, Counter>,
+}
+
+impl ScrapeMetrics {
+    pub fn new() -> Self {
+        let metrics = Self::default();
+        let kinds = [error_kind::session, error_kind::traffic, error_kind::decode, error_kind::network, error_kind::timeout];
+        for kind in kinds {
+            let _ = metrics.errors.get_or_create(&ErrorLabels { kind });
+        }
+        metrics
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.success.get() == 1
+    }
+
+    pub fn ever_succeeded(&self) -> bool {
+        self.ever_succeeded.load(Ordering::Relaxed)
+    }
+
+    fn observe(&self, duration: Duration, err: Option<&ModemError>) {
+        self.duration.set(duration.as_secs_f64());
+        self.success.set(err.is_none() as i64);
+        if err.is_none() {
+            self.ever_succeeded.store(true, Ordering::Relaxed);
+        }
+        if let Some(err) = err {
+            self.errors.get_or_create(&ErrorLabels { kind: error_kind::classify(err) }).inc();
+        }
+    }
+
+    fn register(&self, registry: &mut Registry) {
+        registry.register(
+            "scrape_success", "Whether the last scrape of the modem succeeded",
+            self.success.clone(),
+        );
+        registry.register_with_unit(
+            "scrape_duration", "Duration of the last scrape of the modem",
+            Unit::Seconds, self.duration.clone(),
+        );
+        registry.register(
+            "scrape_errors", "Failed scrapes of the modem by error kind",
+            self.errors.clone(),
+        );
+    }
+}
+
+#[derive(Debug)]
+struct BuildInfo;
+
+impl Collector for BuildInfo {
+    #[allow(non_camel_case_types)]
+    fn encode(&self, mut encoder: DescriptorEncoder) -> std::result::Result<(), Error> {
+        #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+        struct labels {
+            version: &'static str,
+            revision: &'static str,
+            rustc_version: &'static str,
+        }
+
+        encoder.encode_descriptor(
+            "exporter_build_info", "Exporter build information",
+            None, MetricType::Gauge,
+        )?.encode_family(&labels {
+            version: env!("CARGO_PKG_VERSION"),
+            revision: env!("GIT_COMMIT"),
+            rustc_version: env!("RUSTC_VERSION"),
+        })?.encode_gauge(&1i64)?;
+
+        Ok(())
+    }
+}
+
+fn register_exporter_metrics(registry: &mut Registry, modem: &Modem, scrape: &ScrapeMetrics) {
+    registry.register_collector(Box::new(BuildInfo));
+    scrape.register(registry);
+    registry.register(
+        "request_retries", "Retried requests to the modem",
+        modem.retries.clone(),
+    );
+    registry.register(
+        "decode_errors", "Responses that failed to deserialize",
+        modem.decode_errors.clone(),
+    );
+    registry.register_with_unit(
+        "request_duration", "Duration of requests to the modem",
+        Unit::Seconds, modem.request_duration.clone(),
+    );
+    registry.register(
+        "reconnects", "Detected reconnects of the mobile connection",
+        modem.reconnects.clone(),
+    );
+    if let Some(session_start) = *modem.session_start.lock().unwrap() {
+        registry.register_with_unit(
+            "session_start_timestamp", "Start time of the current connection",
+            Unit::Seconds, ConstGauge::new(session_start),
+        );
+    }
+    if let Some(totals) = modem.lifetime.as_ref().and_then(|lifetime| lifetime.totals()) {
+        registry.register_collector(Box::new(totals));
+    }
+    if let Some(samples) = modem.take_signal_samples() {
+        registry.register_collector(Box::new(samples));
+    }
+}
+
+pub const DEFAULT_METRIC_PREFIX: &str = "modem_";
+
+fn registry(prefix: &str, labels: &[(String, String)]) -> Registry {
+    let labels = labels.iter().map(|(name, value)| {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        (name.clone().into(), value.into())
+    });
+    match prefix.trim_end_matches('_') {
+        "" => Registry::with_labels(labels),
+        prefix => Registry::with_prefix_and_labels(prefix, labels),
+    }
+}
+
+fn encode_registry(registry: &Registry) -> Result<String> {
+    let mut data = String::new();
+    encode(&mut data, registry).context("failed to encode")?;
+    Ok(data)
+}
+
+fn encode_collector_with(prefix: &str, labels: &[(String, String)], collector: Box<dyn Collector>) -> Result<String> {
+    let mut registry = registry(prefix, labels);
+    registry.register_collector(collector);
+    encode_registry(&registry)
+}
+
+pub fn encode_collector(collector: Box<dyn Collector>) -> Result<String> {
+    encode_collector_with(DEFAULT_METRIC_PREFIX, &[], collector)
+}
+
+pub fn encode_modem_collector(modem: &Modem, collector: Box<dyn Collector>) -> Result<String> {
+    encode_collector_with(&modem.metric_prefix, &modem.labels, collector)
+}
+
+pub struct StatisticsCache {
+    ttl: Option<Duration>,
+    last: std::sync::Mutex<Option<(Instant, Statistics)>>,
+}
+
+impl StatisticsCache {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            last: Default::default(),
+        }
+    }
+
+    fn store(&self, stats: &Statistics) {
+        if self.ttl.is_some() {
+            *self.last.lock().unwrap() = Some((Instant::now(), stats.clone()));
+        }
+    }
+
+    fn load(&self) -> Option<(Statistics, Duration)> {
+        let ttl = self.ttl?;
+        let last = self.last.lock().unwrap();
+        let (at, stats) = last.as_ref()?;
+        let age = at.elapsed();
+        (age <= ttl).then(|| (stats.clone(), age))
+    }
+}
+
+pub const COLLECTORS: [&str; 17] = [
+    "traffic", "signal", "device", "sms", "status", "month", "pin", "wlan_hosts", "wlan_settings", "plmn",
+    "notifications", "start_date", "net_mode", "data_switch", "antenna", "wan", "firmware_update",
+];
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CollectorLabels {
+    collector: &'static str,
+}
+
+fn collectors(stats: Statistics) -> [(&'static str, Option<Box<dyn Collector>>); COLLECTORS.len()] {
+    [
+        ("traffic", stats.traffic.map(|stats| Box::new(stats) as _)),
+        ("signal", stats.signal.map(|stats| Box::new(stats) as _)),
+        ("device", stats.device.map(|stats| Box::new(stats) as _)),
+        ("sms", stats.sms.map(|stats| Box::new(stats) as _)),
+        ("status", stats.status.map(|stats| Box::new(stats) as _)),
+        ("month", stats.month.map(|stats| Box::new(stats) as _)),
+        ("pin", stats.pin.map(|stats| Box::new(stats) as _)),
+        ("wlan_hosts", stats.wlan_hosts.map(|stats| Box::new(stats) as _)),
+        ("wlan_settings", stats.wlan_settings.map(|stats| Box::new(stats) as _)),
+        ("plmn", stats.plmn.map(|stats| Box::new(stats) as _)),
+        ("notifications", stats.notifications.map(|stats| Box::new(stats) as _)),
+        ("start_date", stats.start_date.map(|stats| Box::new(stats) as _)),
+        ("net_mode", stats.net_mode.map(|stats| Box::new(stats) as _)),
+        ("data_switch", stats.data_switch.map(|stats| Box::new(stats) as _)),
+        ("antenna", stats.antenna.map(|stats| Box::new(stats) as _)),
+        ("wan", stats.wan.map(|stats| Box::new(stats) as _)),
+        ("firmware_update", stats.firmware_update.map(|stats| Box::new(stats) as _)),
+    ]
+}
+
+fn register_statistics(registry: &mut Registry, modem: &Modem, mut stats: Statistics) {
+    let converged = stats.converged.take();
+
+    let up = Family::<CollectorLabels, Gauge>::default();
+    for (collector, collected) in collectors(stats) {
+        if !modem.collector_enabled(collector) {
+            continue;
+        }
+        up.get_or_create(&CollectorLabels { collector }).set(collected.is_some() as i64);
+        if let Some(collected) = collected {
+            registry.register_collector(collected);
+        }
+    }
+
+    if let Some(converged) = converged.filter(|_| modem.collector_enabled("status")) {
+        registry.register_collector(Box::new(converged));
+    }
+    registry.register("collector_up", "Whether the collector succeeded", up);
+}
+
+struct Gathered {
+    up: bool,
+    timed_out: bool,
+    stats: Option<(Statistics, Duration)>,
+}
+
+async fn gather(modem: &Modem, scrape: &ScrapeMetrics, cache: &StatisticsCache) -> Gathered {
+    let start = Instant::now();
+    let result = modem.gather_statistics().await;
+    scrape.observe(start.elapsed(), result.as_ref().err());
+
+    let up = result.is_ok();
+    let timed_out = match &result {
+        Ok(stats) => stats.timed_out,
+        Err(err) => matches!(err, ModemError::Timeout),
+    };
+    let stats = match result {
+        Ok(stats) => {
+            cache.store(&stats);
+            Some((stats, Duration::ZERO))
+        }
+        Err(err) => {
+            warn!("scrape failed: {err:#}");
+            cache.load()
+        }
+    };
+    Gathered { up, timed_out, stats }
+}
+
+pub async fn gather_metrics(modem: &Modem, scrape: &ScrapeMetrics, cache: &StatisticsCache) -> Result<String> {
+    let Gathered { up, timed_out, stats } = gather(modem, scrape, cache).await;
+
+    let mut registry = registry(&modem.metric_prefix, &modem.labels);
+    register_exporter_metrics(&mut registry, modem, scrape);
+    registry.register(
+        "up", "Whether the modem API was reachable",
+        ConstGauge::new(up as i64),
+    );
+    registry.register(
+        "scrape_timeout", "Whether the scrape hit the deadline",
+        ConstGauge::new(timed_out as i64),
+    );
+
+    if let Some((stats, age)) = stats {
+        if cache.ttl.is_some() {
+            registry.register(
+                "metrics_stale", "Whether the metrics were served from the cache",
+                ConstGauge::new((age > Duration::ZERO) as i64),
+            );
+            registry.register_with_unit(
+                "metrics_age", "Age of the served metrics",
+                Unit::Seconds, ConstGauge::new(age.as_secs_f64()),
+            );
+        }
+        register_statistics(&mut registry, modem, stats);
+    }
+
+    encode_registry(&registry)
+}
+
+pub async fn check_collectors(modem: &Modem) -> Result<Vec<(&'static str, bool)>, ModemError> {
+    let stats = modem.gather_statistics().await?;
+    Ok(collectors(stats).into_iter()
+        .filter(|(collector, _)| modem.collector_enabled(collector))
+        .map(|(collector, collected)| (collector, collected.is_some()))
+        .collect())
+}
+
+#[derive(Serialize)]
+struct JsonStatistics {
+    up: bool,
+    scrape_timeout: bool,
+    stale: bool,
+    age_seconds: f64,
+    statistics: Option<Statistics>,
+}
+
+pub async fn gather_json(modem: &Modem, scrape: &ScrapeMetrics, cache: &StatisticsCache) -> Result<String> {
+    let Gathered { up, timed_out, stats } = gather(modem, scrape, cache).await;
+    let age = stats.as_ref().map_or(Duration::ZERO, |(_, age)| *age);
+    let json = JsonStatistics {
+        up,
+        scrape_timeout: timed_out,
+        stale: age > Duration::ZERO,
+        age_seconds: age.as_secs_f64(),
+        statistics: stats.map(|(stats, _)| stats),
+    };
+    serde_json::to_string(&json).context("encode json")
+}
@@ -0,0 +1,1652 @@
+use std::fmt;
+use std::future::Future;
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use base64::prelude::*;
+use encoding_rs::{Encoding, UTF_8};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::histogram::Histogram;
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::{redirect, Client, RequestBuilder, StatusCode};
+use reqwest::header::{HeaderMap, ACCEPT_LANGUAGE, CONTENT_TYPE, LOCATION, REFERER, USER_AGENT};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::{DeserializeOwned, Error as _, IgnoredAny};
+use sha2::{Digest, Sha256};
+use tokio::time::sleep;
+use tracing::{debug, info, instrument, warn};
+
+use crate::metrics::{parse_signal_value, DEFAULT_METRIC_PREFIX};
+
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+const PLMN_SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_SMS_LENGTH: usize = 160;
+const TRAFFIC_PATHS: [&str; 2] = ["/api/monitoring/traffic-statistics", "/api/monitoring/statistic"];
+const LOCALIZED_PATHS: [&str; 2] = ["/api/net/current-plmn", "/api/net/plmn-list"];
+const SESSION_ERROR_CODES: [i32; 4] = [100003, 125001, 125002, 125003];
+const SCOPED_HOST: &str = "scoped-modem.invalid";
+const IDENTITY_TAGS: [&str; 5] = ["SerialNumber", "Imei", "Imsi", "Iccid", "Msisdn"];
+
+pub const DEFAULT_ACCEPT_LANGUAGE: &str = "en-US";
+
+pub const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "request")]
+struct ModemRequest<T>(T);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ModemResponse<T = ()> {
+    Response(T),
+    Error {
+        code: i32,
+        message: String,
+    },
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn is_session_error(&self) -> bool {
+        SESSION_ERROR_CODES.contains(&self.code)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "api error: code={} message={}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl<T> ModemResponse<T> {
+    fn ok(self) -> Result<T> {
+        match self {
+            ModemResponse::Response(val) => Ok(val),
+            ModemResponse::Error { code, message } => Err(ApiError { code, message }.into())
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OkResponse;
+
+impl<'de> Deserialize<'de> for OkResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match String::deserialize(deserializer)?.trim() {
+            "OK" => Ok(OkResponse),
+            value => Err(D::Error::custom(format!("unexpected response: {value:?}"))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+struct SessionResponse {
+    #[serde(rename = "SesInfo")]
+    session: String,
+    #[serde(rename = "TokInfo")]
+    token: String,
+}
+
+fn lenient_u64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if let Ok(value) = value.trim().parse() {
+        return Ok(value);
+    }
+
+    let compact = value.chars().filter(|c| !c.is_whitespace() && !matches!(c, ',' | '_')).collect::<String>();
+    let coerced = if !compact.is_empty() && compact.chars().all(|c| c.is_ascii_digit()) {
+        compact.parse().unwrap_or(u64::MAX)
+    } else if let Some(number) = compact.parse::<f64>().ok().filter(|number| !number.is_nan()) {
+        number as u64
+    } else {
+        let start = value.find(|c: char| c.is_ascii_digit())
+            .ok_or_else(|| D::Error::custom(format!("invalid number: {value:?}")))?;
+        let digits = &value[start..];
+        let digits = &digits[..digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len())];
+        match value[..start].ends_with('-') {
+            true => 0,
+            false => digits.parse().unwrap_or(u64::MAX),
+        }
+    };
+    warn!("coerced {value:?} to {coerced}");
+    Ok(coerced)
+}
+
+fn optional_u64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<u64>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.and_then(|value| value.trim().parse().ok()))
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct TrafficStatistics {
+    #[serde(alias = "currentUpload", deserialize_with = "lenient_u64")]
+    pub current_upload: u64,
+    #[serde(alias = "currentDownload", deserialize_with = "lenient_u64")]
+    pub current_download: u64,
+    #[serde(alias = "currentConnectTime", deserialize_with = "lenient_u64")]
+    pub current_connect_time: u64,
+    #[serde(alias = "totalUpload", deserialize_with = "lenient_u64")]
+    pub total_upload: u64,
+    #[serde(alias = "totalDownload", deserialize_with = "lenient_u64")]
+    pub total_download: u64,
+    #[serde(alias = "totalConnectTime", deserialize_with = "lenient_u64")]
+    pub total_connect_time: u64,
+    #[serde(default, alias = "currentUploadRate", deserialize_with = "optional_u64")]
+    pub current_upload_rate: Option<u64>,
+    #[serde(default, alias = "currentDownloadRate", deserialize_with = "optional_u64")]
+    pub current_download_rate: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SignalInfo {
+    pub rssi: Option<String>,
+    pub rsrp: Option<String>,
+    pub rsrq: Option<String>,
+    pub sinr: Option<String>,
+    pub ecio: Option<String>,
+    pub band: Option<String>,
+    pub dlbandwidth: Option<String>,
+    pub ulbandwidth: Option<String>,
+    pub ca: Option<String>,
+    pub antennatype: Option<String>,
+    #[serde(alias = "Temperature")]
+    pub temperature: Option<String>,
+    pub cell_id: Option<String>,
+    pub pci: Option<String>,
+    pub tac: Option<String>,
+    pub lac: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SignalSamples {
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    pub count: u32,
+}
+
+impl SignalSamples {
+    fn record(samples: &mut Option<SignalSamples>, value: f64) {
+        let samples = samples.get_or_insert(SignalSamples { min: value, max: value, sum: 0.0, count: 0 });
+        samples.min = samples.min.min(value);
+        samples.max = samples.max.max(value);
+        samples.sum += value;
+        samples.count += 1;
+    }
+
+    pub fn avg(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct DeviceInformation {
+    #[serde(default)]
+    pub device_name: String,
+    #[serde(default)]
+    pub serial_number: String,
+    #[serde(default)]
+    pub imei: String,
+    #[serde(default)]
+    pub imsi: String,
+    #[serde(default)]
+    pub hardware_version: String,
+    #[serde(default)]
+    pub software_version: String,
+    #[serde(default, rename(deserialize = "WebUIVersion"))]
+    pub webui_version: String,
+    pub uptime: Option<String>,
+    pub iccid: Option<String>,
+    pub msisdn: Option<String>,
+}
+
+impl DeviceInformation {
+    pub fn redacted(self) -> Self {
+        Self {
+            serial_number: String::new(),
+            imei: String::new(),
+            imsi: String::new(),
+            iccid: None,
+            msisdn: None,
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct SmsCount {
+    pub local_unread: u32,
+    pub local_inbox: u32,
+    pub local_outbox: u32,
+    pub local_draft: u32,
+    #[serde(default)]
+    pub sim_unread: u32,
+    #[serde(default)]
+    pub sim_inbox: u32,
+    #[serde(default)]
+    pub sim_outbox: u32,
+    #[serde(default)]
+    pub sim_draft: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct MonitoringStatus {
+    pub connection_status: u32,
+    pub current_network_type: u32,
+    pub roaming_status: u32,
+    pub signal_icon: Option<String>,
+    pub signal_strength: Option<String>,
+    pub battery_status: Option<String>,
+    pub battery_percent: Option<String>,
+    #[serde(alias = "OverheatStatus", alias = "OverHeat", alias = "TemperatureProtection")]
+    pub thermal_protection: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RatSignal {
+    pub rssi: Option<String>,
+    pub rsrp: Option<String>,
+    pub rsrq: Option<String>,
+    pub sinr: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct ConvergedStatus {
+    pub connection_status: u32,
+    pub current_network_type: u32,
+    #[serde(default)]
+    pub roaming_status: u32,
+    pub signal_icon: Option<String>,
+    pub nr_connected: Option<String>,
+    pub nsa_mode: Option<String>,
+    #[serde(rename(deserialize = "LTE"))]
+    pub lte: Option<RatSignal>,
+    #[serde(rename(deserialize = "NR"))]
+    pub nr: Option<RatSignal>,
+    #[serde(alias = "OverheatStatus", alias = "OverHeat", alias = "TemperatureProtection")]
+    pub thermal_protection: Option<String>,
+}
+
+impl ConvergedStatus {
+    pub fn status(&self) -> MonitoringStatus {
+        MonitoringStatus {
+            connection_status: self.connection_status,
+            current_network_type: self.current_network_type,
+            roaming_status: self.roaming_status,
+            signal_icon: self.signal_icon.clone(),
+            signal_strength: None,
+            battery_status: None,
+            battery_percent: None,
+            thermal_protection: self.thermal_protection.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct MonthStatistics {
+    #[serde(alias = "currentMonthDownload", deserialize_with = "lenient_u64")]
+    pub current_month_download: u64,
+    #[serde(alias = "currentMonthUpload", deserialize_with = "lenient_u64")]
+    pub current_month_upload: u64,
+    #[serde(alias = "monthDuration", deserialize_with = "lenient_u64")]
+    pub month_duration: u64,
+    #[serde(default, alias = "monthLastClearTime")]
+    pub month_last_clear_time: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct PinStatus {
+    pub sim_state: u32,
+    #[serde(default, alias = "SimPinTimes")]
+    pub remaining_attempts: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct WlanHost {
+    #[serde(rename(deserialize = "Frequency"), default)]
+    pub frequency: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct WlanHosts {
+    #[serde(rename(deserialize = "Host"), default)]
+    pub host: Vec<WlanHost>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WlanHostList {
+    #[serde(rename(deserialize = "Hosts"), default)]
+    pub hosts: WlanHosts,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct WlanBasicSettings {
+    #[serde(default)]
+    pub wifi_ssid: String,
+    #[serde(default)]
+    pub wifi_channel: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct CurrentPlmn {
+    pub state: Option<String>,
+    #[serde(default)]
+    pub full_name: String,
+    #[serde(default)]
+    pub short_name: String,
+    #[serde(default)]
+    pub numeric: String,
+    pub rat: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AvailableNetwork {
+    pub state: Option<String>,
+    #[serde(default)]
+    pub full_name: String,
+    #[serde(default)]
+    pub short_name: String,
+    #[serde(default)]
+    pub numeric: String,
+    pub rat: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AvailableNetworks {
+    #[serde(rename = "Network", default)]
+    pub network: Vec<AvailableNetwork>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct PlmnList {
+    #[serde(rename = "Networks", default)]
+    networks: AvailableNetworks,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DataSwitch {
+    pub dataswitch: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct CheckNotifications {
+    pub unread_message: Option<u32>,
+    pub sms_storage_full: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct FirmwareUpdate {
+    #[serde(default, alias = "Version")]
+    pub new_version: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct StartDate {
+    pub start_day: Option<u32>,
+    pub data_limit: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct NetMode {
+    pub network_mode: String,
+    pub network_band: String,
+    #[serde(rename(deserialize = "LTEBand"))]
+    pub lte_band: String,
+}
+
+impl NetMode {
+    pub fn validate(&self) -> Result<(), ModemError> {
+        let is_hex = |mask: &str| !mask.is_empty() && mask.len() <= 16 && mask.chars().all(|c| c.is_ascii_hexdigit());
+        if self.network_mode.is_empty() || !self.network_mode.len().is_multiple_of(2)
+            || !self.network_mode.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ModemError::Other(anyhow!("invalid network mode: {:?}", self.network_mode)));
+        }
+        if !is_hex(&self.network_band) {
+            return Err(ModemError::Other(anyhow!("invalid network band mask: {:?}", self.network_band)));
+        }
+        if !is_hex(&self.lte_band) {
+            return Err(ModemError::Other(anyhow!("invalid LTE band mask: {:?}", self.lte_band)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Sms {
+    pub number: String,
+    pub text: String,
+}
+
+impl Sms {
+    pub fn validate(&self) -> Result<(), ModemError> {
+        let number = self.number.strip_prefix('+').unwrap_or(&self.number);
+        if number.is_empty() || number.len() > 20 || !number.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ModemError::Other(anyhow!("invalid phone number: {:?}", self.number)));
+        }
+        if self.text.is_empty() {
+            return Err(ModemError::Other(anyhow!("empty message")));
+        }
+        if self.text.chars().count() > MAX_SMS_LENGTH {
+            return Err(ModemError::Other(anyhow!("message longer than {MAX_SMS_LENGTH} characters")));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Antenna {
+    pub antennatype: Option<String>,
+    pub antenna_settings: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct WanInfo {
+    #[serde(rename(deserialize = "WanIPAddress"), alias = "IPAddress")]
+    pub wan_ip_address: Option<String>,
+    #[serde(alias = "PrimaryDNS")]
+    pub primary_dns: Option<String>,
+    #[serde(alias = "SecondaryDNS")]
+    pub secondary_dns: Option<String>,
+    #[serde(rename(deserialize = "APN"), alias = "Apn")]
+    pub apn: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LifetimeTotals {
+    pub upload: u64,
+    pub download: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LifetimeState {
+    last: Option<LifetimeTotals>,
+    carried: LifetimeTotals,
+}
+
+#[derive(Debug, Default)]
+pub struct LifetimeTraffic {
+    path: Option<PathBuf>,
+    state: std::sync::Mutex<LifetimeState>,
+}
+
+impl LifetimeTraffic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let state = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("parse state file {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => LifetimeState::default(),
+            Err(err) => return Err(anyhow::Error::new(err).context(format!("read state file {}", path.display()))),
+        };
+        Ok(Self { path: Some(path), state: std::sync::Mutex::new(state) })
+    }
+
+    pub fn observe(&self, current: LifetimeTotals) -> LifetimeTotals {
+        let mut state = self.state.lock().unwrap();
+        if let Some(last) = state.last {
+            if current.upload < last.upload {
+                state.carried.upload = state.carried.upload.saturating_add(last.upload);
+            }
+            if current.download < last.download {
+                state.carried.download = state.carried.download.saturating_add(last.download);
+            }
+        }
+        state.last = Some(current);
+        LifetimeTotals {
+            upload: state.carried.upload.saturating_add(current.upload),
+            download: state.carried.download.saturating_add(current.download),
+        }
+    }
+
+    pub fn totals(&self) -> Option<LifetimeTotals> {
+        let state = self.state.lock().unwrap();
+        let last = state.last?;
+        Some(LifetimeTotals {
+            upload: state.carried.upload.saturating_add(last.upload),
+            download: state.carried.download.saturating_add(last.download),
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        static SAVES: AtomicU64 = AtomicU64::new(0);
+
+        let state = self.state.lock().unwrap();
+        let data = serde_json::to_vec(&*state).context("serialize state")?;
+        let tmp = path.with_extension(format!("{}.{}.tmp", std::process::id(), SAVES.fetch_add(1, Ordering::Relaxed)));
+        std::fs::write(&tmp, data).with_context(|| format!("write state file {}", tmp.display()))?;
+        if let Err(err) = std::fs::rename(&tmp, path) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(anyhow::Error::new(err).context(format!("replace state file {}", path.display())));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Statistics {
+    pub traffic: Option<TrafficStatistics>,
+    pub signal: Option<SignalInfo>,
+    pub device: Option<DeviceInformation>,
+    pub sms: Option<SmsCount>,
+    pub status: Option<MonitoringStatus>,
+    pub converged: Option<ConvergedStatus>,
+    pub month: Option<MonthStatistics>,
+    pub pin: Option<PinStatus>,
+    pub wlan_hosts: Option<WlanHostList>,
+    pub wlan_settings: Option<WlanBasicSettings>,
+    pub plmn: Option<CurrentPlmn>,
+    pub data_switch: Option<DataSwitch>,
+    pub notifications: Option<CheckNotifications>,
+    pub start_date: Option<StartDate>,
+    pub net_mode: Option<NetMode>,
+    pub antenna: Option<Antenna>,
+    pub wan: Option<WanInfo>,
+    pub firmware_update: Option<FirmwareUpdate>,
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct LoginRequest {
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Password")]
+    password: String,
+    password_type: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct ChallengeRequest {
+    username: String,
+    firstnonce: String,
+    mode: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChallengeResponse {
+    salt: String,
+    iterations: u32,
+    servernonce: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AuthenticationRequest {
+    clientproof: String,
+    finalnonce: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ControlRequest {
+    #[serde(rename = "Control")]
+    control: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct Phones {
+    #[serde(rename = "Phone")]
+    phone: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendSmsRequest {
+    index: i32,
+    phones: Phones,
+    sca: String,
+    content: String,
+    length: usize,
+    reserved: u32,
+    date: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct NetModeRequest<'a> {
+    network_mode: &'a str,
+    network_band: &'a str,
+    #[serde(rename = "LTEBand")]
+    lte_band: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct LogoutRequest {
+    #[serde(rename = "Logout")]
+    logout: u32,
+}
+
+#[derive(Clone)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+    let era = (days + 719468).div_euclid(146097);
+    let doe = (days + 719468).rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn sha256_hex(data: &str) -> String {
+    hex(&Sha256::digest(data.as_bytes()))
+}
+
+fn client_nonce() -> Result<String> {
+    let mut nonce = [0u8; 32];
+    getrandom::fill(&mut nonce).map_err(|err| anyhow!("generate nonce: {err}"))?;
+    Ok(hex(&nonce))
+}
+
+fn scram_client_proof(password: &str, nonce: &str, challenge: &ChallengeResponse) -> Result<String> {
+    let salt = (0..challenge.salt.len()).step_by(2)
+        .map(|i| challenge.salt.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .with_context(|| format!("invalid salt: {:?}", challenge.salt))?;
+    let message = format!("{nonce},{},{}", challenge.servernonce, challenge.servernonce);
+
+    let mut salted_password = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, challenge.iterations, &mut salted_password);
+    let client_key = hmac_sha256(b"Client Key", &salted_password);
+    let stored_key = Sha256::digest(client_key);
+    let signature = hmac_sha256(message.as_bytes(), &stored_key);
+
+    let proof = client_key.iter().zip(signature).map(|(key, sig)| key ^ sig).collect::<Vec<_>>();
+    Ok(hex(&proof))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn is_unsupported(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ApiError>().is_some_and(|err| err.code == 100002)
+        || err.downcast_ref::<reqwest::Error>().and_then(reqwest::Error::status) == Some(StatusCode::NOT_FOUND)
+}
+
+fn csrf_token(html: &str) -> Option<String> {
+    html.split("<meta").skip(1)
+        .map(|tag| tag.split('>').next().unwrap_or_default())
+        .find(|tag| tag.contains(r#"name="csrf_token""#))
+        .and_then(|tag| tag.split(r#"content=""#).nth(1)?.split('"').next())
+        .map(str::to_string)
+}
+
+fn is_identity_tag(name: &str) -> bool {
+    IDENTITY_TAGS.iter().any(|tag| tag.eq_ignore_ascii_case(name)) || name.to_ascii_lowercase().contains("macaddr")
+}
+
+fn redact_identity_tags(xml: &str) -> String {
+    let mut redacted = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>').map(|end| start + end + 1) else {
+            break;
+        };
+        let tag = &rest[start..end];
+        redacted.push_str(&rest[..end]);
+        rest = &rest[end..];
+        let name = tag[1..tag.len() - 1].split_whitespace().next().unwrap_or_default();
+        if !tag.ends_with("/>") && is_identity_tag(name) {
+            if let Some(close) = rest.find(&format!("</{name}>")) {
+                rest = &rest[close..];
+            }
+        }
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+fn login_password_hash(username: &str, password: &str, token: &str) -> String {
+    let password = BASE64_STANDARD.encode(sha256_hex(password));
+    BASE64_STANDARD.encode(sha256_hex(&format!("{username}{password}{token}")))
+}
+
+pub fn normalize_base_url(url: &str) -> String {
+    let (scheme, rest) = url.trim().split_once("://").unwrap_or(("http", url.trim()));
+    let rest = rest.trim_end_matches('/');
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let (address, zone, port) = match host.strip_prefix('[').and_then(|host| host.split_once(']')) {
+        Some((address, port)) => match address.split_once('%') {
+            Some((address, zone)) => (address, Some(zone.strip_prefix("25").unwrap_or(zone)), port),
+            None => (address, None, port),
+        },
+        None => match host.split_once('%') {
+            Some((address, zone)) => (address, Some(zone), ""),
+            None => (host, None, ""),
+        },
+    };
+    match (Ipv6Addr::from_str(address), zone) {
+        (Ok(_), Some(zone)) => format!("{scheme}://[{address}%25{zone}]{port}{path}"),
+        (Ok(_), None) => format!("{scheme}://[{address}]{port}{path}"),
+        (Err(_), _) => format!("{scheme}://{host}{path}"),
+    }
+}
+
+fn scoped_address(base_url: &str) -> Result<Option<(String, SocketAddr)>> {
+    let Some((scheme, rest)) = base_url.split_once("://") else {
+        return Ok(None);
+    };
+    let Some((host, rest)) = rest.strip_prefix('[').and_then(|rest| rest.split_once(']')) else {
+        return Ok(None);
+    };
+    let Some((address, zone)) = host.split_once("%25") else {
+        return Ok(None);
+    };
+    let address = Ipv6Addr::from_str(address).with_context(|| format!("invalid modem address: {base_url}"))?;
+    let scope_id = match zone.parse() {
+        Ok(scope_id) => scope_id,
+        Err(_) => Some(zone)
+            .filter(|zone| !zone.is_empty() && !zone.starts_with('.') && !zone.contains('/'))
+            .and_then(|zone| std::fs::read_to_string(format!("/sys/class/net/{zone}/ifindex")).ok())
+            .and_then(|index| index.trim().parse().ok())
+            .with_context(|| format!("unknown interface in modem address: {zone:?}"))?,
+    };
+    let url = format!("{scheme}://{SCOPED_HOST}{rest}");
+    Ok(Some((url, SocketAddr::V6(SocketAddrV6::new(address, 0, 0, scope_id)))))
+}
+
+fn is_retryable(err: &anyhow::Error) -> bool {
+    if let Some(err) = err.chain().find_map(|err| err.downcast_ref::<reqwest::Error>()) {
+        return match err.status() {
+            Some(status) => status.is_server_error(),
+            None => !err.is_builder(),
+        };
+    }
+    err.downcast_ref::<ApiError>().is_some_and(|err| matches!(err.code, 100004 | 100005))
+}
+
+async fn within<T>(deadline: Option<Instant>, future: impl Future<Output = T>) -> Option<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+async fn bounded<T: Default>(
+    deadline: Option<Instant>, timed_out: &AtomicBool, request: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match within(deadline, request).await {
+        Some(result) => result,
+        None => {
+            timed_out.store(true, Ordering::Relaxed);
+            Ok(T::default())
+        }
+    }
+}
+
+fn is_unreachable(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|err| err.downcast_ref::<reqwest::Error>())
+        .any(|err| err.is_connect() || err.is_timeout())
+}
+
+fn is_certificate_error(err: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if err.to_string().to_lowercase().contains("certificate") {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModemError {
+    #[error("{0:#}")]
+    Network(anyhow::Error),
+    #[error("{0:#}")]
+    Auth(anyhow::Error),
+    #[error("api error: code={code} message={message}")]
+    ApiError { code: i32, message: String },
+    #[error("{0:#}")]
+    Decode(anyhow::Error),
+    #[error("session expired, re-auth required")]
+    SessionExpired,
+    #[error("scrape deadline exceeded")]
+    Timeout,
+    #[error("{0:#}")]
+    Other(anyhow::Error),
+}
+
+impl ModemError {
+    pub fn is_session_expired(&self) -> bool {
+        match self {
+            ModemError::SessionExpired => true,
+            ModemError::ApiError { code, .. } => SESSION_ERROR_CODES.contains(code),
+            _ => false,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ModemError {
+    fn from(err: anyhow::Error) -> Self {
+        if err.chain().any(|err| err.is::<reqwest::Error>()) {
+            ModemError::Network(err)
+        } else if err.chain().any(|err| err.is::<quick_xml::DeError>()) {
+            ModemError::Decode(err)
+        } else if err.is::<SessionError>() {
+            ModemError::Auth(err)
+        } else if let Some(ApiError { code, message }) = err.downcast_ref::<ApiError>() {
+            ModemError::ApiError { code: *code, message: message.clone() }
+        } else if err.is::<SessionExpired>() {
+            ModemError::SessionExpired
+        } else {
+            ModemError::Other(err)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SessionExpired;
+
+impl fmt::Display for SessionExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("session expired, re-auth required")
+    }
+}
+
+impl std::error::Error for SessionExpired {}
+
+pub(crate) fn is_session_expired(err: &anyhow::Error) -> bool {
+    err.is::<SessionExpired>() || err.downcast_ref::<ApiError>().is_some_and(ApiError::is_session_error)
+}
+
+fn declared_encoding(body: &[u8]) -> Option<&'static Encoding> {
+    let prolog = body.strip_prefix(b"<?xml")?;
+    let prolog = &prolog[..prolog.windows(2).position(|window| window == b"?>")?];
+    let prolog = std::str::from_utf8(prolog).ok()?;
+    let label = prolog.split_once("encoding")?.1.trim_start().strip_prefix('=')?.trim_start();
+    let quote = label.chars().next().filter(|quote| matches!(quote, '"' | '\''))?;
+    let label = label[1..].split(quote).next()?;
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+fn decode<Resp: DeserializeOwned>(resp: &Response) -> Result<Resp> {
+    if resp.status.is_redirection() {
+        debug!(location = ?resp.headers.get(LOCATION), "api request redirected");
+        return Err(SessionExpired.into());
+    }
+    let is_html = resp.headers.get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("html"));
+    let body = resp.body.trim_ascii_start();
+    if is_html || ![&b"<?xml"[..], b"<response", b"<error"].iter().any(|prefix| body.starts_with(prefix)) {
+        return Err(SessionExpired.into());
+    }
+    match declared_encoding(body).filter(|encoding| *encoding != UTF_8) {
+        Some(encoding) => {
+            let (body, _) = encoding.decode_without_bom_handling(body);
+            quick_xml::de::from_str(&body).with_context(|| format!("deserialize {} response", encoding.name()))
+        }
+        None => quick_xml::de::from_reader(body).context("deserialize response"),
+    }
+}
+
+const REQUEST_DURATION_BUCKETS: [f64; 10] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 5.0];
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub(crate) struct EndpointLabels {
+    endpoint: String,
+}
+
+pub struct Modem {
+    client: Client,
+    base_url: String,
+    insecure_tls: bool,
+    user_agent: String,
+    accept_language: String,
+    credentials: Option<Credentials>,
+    session: std::sync::Mutex<Option<SessionResponse>>,
+    auth: tokio::sync::Mutex<()>,
+    max_retries: u32,
+    collectors: Option<Vec<String>>,
+    redact_identity: bool,
+    scrape_deadline: Option<Duration>,
+    pub(crate) metric_prefix: String,
+    pub(crate) labels: Vec<(String, String)>,
+    pub(crate) retries: Counter,
+    pub(crate) decode_errors: Family<EndpointLabels, Counter>,
+    pub(crate) request_duration: Family<EndpointLabels, Histogram, fn() -> Histogram>,
+    tracking: std::sync::Mutex<()>,
+    last_connect_time: std::sync::Mutex<Option<u64>>,
+    last_traffic: std::sync::Mutex<Option<(Instant, u64, u64)>>,
+    pub(crate) reconnects: Counter,
+    pub(crate) session_start: std::sync::Mutex<Option<f64>>,
+    pub(crate) lifetime: Option<LifetimeTraffic>,
+    signal_samples: std::sync::Mutex<Option<SignalSamples>>,
+    traffic_path: Option<String>,
+    found_traffic_path: std::sync::Mutex<Option<String>>,
+}
+
+impl Modem {
+    pub fn new(base_url: impl Into<String>, timeout: Duration, insecure_tls: bool) -> Result<Modem, ModemError> {
+        let base_url = normalize_base_url(&base_url.into());
+
+        let mut builder = Client::builder()
+            .connect_timeout(timeout)
+            .timeout(timeout)
+            .danger_accept_invalid_certs(insecure_tls)
+            .redirect(redirect::Policy::none());
+        let base_url = match scoped_address(&base_url).map_err(ModemError::Other)? {
+            Some((url, address)) => {
+                builder = builder.resolve(SCOPED_HOST, address);
+                url
+            }
+            None => base_url,
+        };
+        let client = builder
+            .build()
+            .context("build http client")
+            .map_err(ModemError::Other)?;
+
+        Ok(Self {
+            client,
+            base_url,
+            insecure_tls,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            accept_language: DEFAULT_ACCEPT_LANGUAGE.to_string(),
+            credentials: None,
+            session: Default::default(),
+            auth: Default::default(),
+            max_retries: 0,
+            collectors: None,
+            redact_identity: false,
+            scrape_deadline: None,
+            metric_prefix: DEFAULT_METRIC_PREFIX.to_string(),
+            labels: Vec::new(),
+            retries: Counter::default(),
+            decode_errors: Family::default(),
+            request_duration: Family::new_with_constructor(|| Histogram::new(REQUEST_DURATION_BUCKETS.into_iter())),
+            tracking: Default::default(),
+            last_connect_time: Default::default(),
+            last_traffic: Default::default(),
+            reconnects: Counter::default(),
+            session_start: Default::default(),
+            lifetime: None,
+            signal_samples: Default::default(),
+            traffic_path: None,
+            found_traffic_path: Default::default(),
+        })
+    }
+
+    pub fn with_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: Option<String>) -> Self {
+        if let Some(user_agent) = user_agent {
+            self.user_agent = user_agent;
+        }
+        self
+    }
+
+    pub fn with_accept_language(mut self, accept_language: Option<String>) -> Self {
+        if let Some(accept_language) = accept_language {
+            self.accept_language = accept_language;
+        }
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: Option<Credentials>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    pub fn with_collectors(mut self, collectors: Option<Vec<String>>) -> Self {
+        self.collectors = collectors;
+        self
+    }
+
+    pub fn with_redact_identity(mut self, redact_identity: bool) -> Self {
+        self.redact_identity = redact_identity;
+        self
+    }
+
+    pub fn with_scrape_deadline(mut self, scrape_deadline: Option<Duration>) -> Self {
+        self.scrape_deadline = scrape_deadline;
+        self
+    }
+
+    pub fn with_metric_prefix(mut self, metric_prefix: impl Into<String>) -> Self {
+        self.metric_prefix = metric_prefix.into();
+        self
+    }
+
+    pub fn with_labels(mut self, labels: Vec<(String, String)>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_traffic_path(mut self, traffic_path: Option<String>) -> Self {
+        self.traffic_path = traffic_path;
+        self
+    }
+
+    pub fn with_lifetime_traffic(mut self, lifetime: Option<LifetimeTraffic>) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    pub(crate) fn collector_enabled(&self, collector: &str) -> bool {
+        self.collectors.as_ref().is_none_or(|collectors| collectors.iter().any(|name| name == collector))
+    }
+
+    fn session(&self) -> Option<SessionResponse> {
+        self.session.lock().unwrap().clone()
+    }
+
+    fn set_session(&self, session: Option<SessionResponse>) {
+        *self.session.lock().unwrap() = session;
+    }
+
+    fn invalidate_session(&self, stale: &Option<SessionResponse>) {
+        let mut session = self.session.lock().unwrap();
+        if *session == *stale {
+            *session = None;
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    async fn send(&self, path: &str, mut builder: RequestBuilder) -> Result<Response> {
+        builder = builder.header(USER_AGENT, &self.user_agent)
+            .header(ACCEPT_LANGUAGE, &self.accept_language);
+        if LOCALIZED_PATHS.contains(&path) {
+            let lang = self.accept_language.split([',', ';']).next().unwrap_or_default().trim();
+            builder = builder.query(&[("lang", lang.to_ascii_lowercase())]);
+        }
+        if path.starts_with("/api/") {
+            builder = builder.header(REFERER, self.url("/html/home.html"));
+        }
+        if let Some(session) = self.session() {
+            builder = builder.header("Cookie", session.session)
+                .header("__RequestVerificationToken", session.token);
+        }
+
+        let start = Instant::now();
+        let result = async {
+            let resp = builder.send().await?.error_for_status()?;
+            let (status, headers) = (resp.status(), resp.headers().clone());
+            Ok(Response { status, headers, body: resp.bytes().await?.into() })
+        }.await;
+        self.request_duration.get_or_create(&EndpointLabels { endpoint: path.to_string() })
+            .observe(start.elapsed().as_secs_f64());
+        match result {
+            Ok(resp) => {
+                debug!(status = %resp.status, elapsed = ?start.elapsed(), "request finished");
+                Ok(resp)
+            }
+            Err(err) => {
+                warn!(elapsed = ?start.elapsed(), "request failed: {err}");
+                if !self.insecure_tls && self.base_url.starts_with("https://") && is_certificate_error(&err) {
+                    return Err(anyhow::Error::new(err).context(
+                        "modem certificate verification failed; set MODEM_INSECURE_TLS=1 to accept \
+                        self-signed certificates, at the cost of no protection against man-in-the-middle attacks"
+                    ));
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, resp), fields(status = %resp.status))]
+    fn decode<Resp: DeserializeOwned>(&self, path: &str, resp: Response) -> Result<Resp> {
+        let result = decode(&resp);
+        if let Err(err) = &result {
+            if err.chain().any(|err| err.is::<quick_xml::DeError>()) {
+                self.decode_errors.get_or_create(&EndpointLabels { endpoint: path.to_string() }).inc();
+            }
+        }
+        result
+    }
+
+    async fn get<Resp: DeserializeOwned>(&self, path: &str) -> Result<Resp> {
+        self.decode(path, self.send(path, self.client.get(self.url(path))).await?)
+    }
+
+    async fn retry<T, F: Future<Output = Result<T>>>(&self, mut request: impl FnMut() -> F) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Err(err) if attempt < self.max_retries && is_retryable(&err) => {
+                    attempt += 1;
+                    warn!(attempt, "retrying request: {err:#}");
+                    self.retries.inc();
+                    sleep(Duration::from_millis(100 << attempt)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn api_get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.retry(|| async move { self.get::<ModemResponse<T>>(path).await?.ok() }).await
+    }
+
+    async fn api_get_partial<T: DeserializeOwned>(&self, path: &str) -> Result<Result<T>> {
+        match self.api_get(path).await {
+            Err(err) if is_session_expired(&err) || is_unreachable(&err) => Err(err),
+            result => Ok(result),
+        }
+    }
+
+    async fn api_get_optional<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        Ok(self.api_get_partial(path).await?.map_err(|err| debug!("{path} unavailable: {err:#}")).ok())
+    }
+
+    async fn api_get_collector<T: DeserializeOwned>(&self, collector: &str, path: &str) -> Result<Option<T>> {
+        if !self.collector_enabled(collector) {
+            return Ok(None);
+        }
+        Ok(self.api_get_partial(path).await?.map_err(|err| warn!("{path} failed: {err:#}")).ok())
+    }
+
+    #[instrument(level = "debug", skip(self, req))]
+    async fn send_post<Req: Serialize>(&self, path: &str, req: Req) -> Result<Response> {
+        let body = quick_xml::se::to_string(&ModemRequest(req)).context("serialize body")?;
+        self.send(path, self.client.post(self.url(path)).body(format!("{XML_DECLARATION}{body}"))).await
+    }
+
+    async fn post<Req: Serialize, Resp: DeserializeOwned>(&self, path: &str, req: Req) -> Result<Resp> {
+        self.decode(path, self.send_post(path, req).await?)
+    }
+
+    async fn post_session<Req: Serialize, Resp: DeserializeOwned>(&self, path: &str, req: Req) -> Result<Resp> {
+        let resp = self.send_post(path, req).await?;
+
+        let header = |name: &str| resp.headers.get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let cookie = header("Set-Cookie")
+            .map(|cookie| cookie.split(';').next().unwrap_or_default().to_string());
+        let token = header("__RequestVerificationTokenone")
+            .or_else(|| header("__RequestVerificationToken"))
+            .map(|token| token.split('#').next().unwrap_or_default().to_string());
+
+        {
+            let mut session = self.session.lock().unwrap();
+            let session = session.as_mut().context("no session")?;
+            if let Some(cookie) = cookie {
+                session.session = cookie;
+            }
+            if let Some(token) = token {
+                session.token = token;
+            }
+        }
+
+        self.decode::<ModemResponse<Resp>>(path, resp)?.ok()
+    }
+
+    pub async fn login(&self) -> Result<(), ModemError> {
+        let _auth = self.auth.lock().await;
+        Ok(self.refresh_session().await.context(SessionError)?)
+    }
+
+    #[instrument(skip(self))]
+    async fn authenticate(&self) -> Result<()> {
+        let Some(credentials) = self.credentials.clone() else {
+            return Ok(());
+        };
+
+        let nonce = client_nonce()?;
+        let challenge = self.post_session::<_, ChallengeResponse>("/api/user/challenge_login", ChallengeRequest {
+            username: credentials.username.clone(),
+            firstnonce: nonce.clone(),
+            mode: 1,
+        }).await;
+
+        match challenge {
+            Ok(challenge) => {
+                let clientproof = scram_client_proof(&credentials.password, &nonce, &challenge)?;
+                self.post_session::<_, IgnoredAny>("/api/user/authentication_login", AuthenticationRequest {
+                    clientproof,
+                    finalnonce: challenge.servernonce,
+                }).await.context("login")?;
+            }
+            Err(err) if is_unsupported(&err) => {
+                debug!("challenge login unsupported, falling back to password login");
+                let token = self.session().context("no session")?.token;
+                let req = LoginRequest {
+                    password: login_password_hash(&credentials.username, &credentials.password, &token),
+                    username: credentials.username,
+                    password_type: 4,
+                };
+                self.post_session::<_, OkResponse>("/api/user/login", req).await.context("login")?;
+            }
+            Err(err) => return Err(err.context("challenge login")),
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn logout(&self) -> Result<(), ModemError> {
+        let _auth = self.auth.lock().await;
+        if self.session().is_none() || self.credentials.is_none() {
+            self.set_session(None);
+            return Ok(());
+        }
+
+        let result = self.post::<_, ModemResponse<OkResponse>>("/api/user/logout", LogoutRequest { logout: 1 }).await;
+        self.set_session(None);
+        result?.ok().context("logout")?;
+        Ok(())
+    }
+
+    async fn ensure_session(&self) -> Result<()> {
+        if self.session().is_none() {
+            let _auth = self.auth.lock().await;
+            if self.session().is_none() {
+                self.refresh_session().await.context(SessionError)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn control<Req: Serialize>(&self, path: &str, req: Req) -> Result<()> {
+        self.ensure_session().await?;
+        let _auth = self.auth.lock().await;
+        let mut result = self.post_session::<_, OkResponse>(path, &req).await;
+        if result.as_ref().is_err_and(is_session_expired) {
+            debug!("session expired, refreshing");
+            self.refresh_session().await.context(SessionError)?;
+            result = self.post_session::<_, OkResponse>(path, &req).await;
+        }
+        if result.as_ref().is_err_and(is_session_expired) {
+            self.set_session(None);
+        }
+        result?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn reboot(&self) -> Result<(), ModemError> {
+        self.control("/api/device/control", ControlRequest { control: 1 }).await.context("reboot")?;
+        self.set_session(None);
+        Ok(())
+    }
+
+    #[instrument(skip(self, sms))]
+    pub async fn send_sms(&self, sms: &Sms) -> Result<(), ModemError> {
+        sms.validate()?;
+        let req = SendSmsRequest {
+            index: -1,
+            phones: Phones { phone: vec![sms.number.clone()] },
+            sca: String::new(),
+            content: sms.text.clone(),
+            length: sms.text.chars().count(),
+            reserved: 1,
+            date: format_timestamp(SystemTime::now()),
+        };
+        Ok(self.control("/api/sms/send-sms", req).await.context("send sms")?)
+    }
+
+    pub async fn get_net_mode(&self) -> Result<NetMode, ModemError> {
+        self.ensure_session().await?;
+        Ok(self.api_get("/api/net/net-mode").await?)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn set_net_mode(&self, mode: &NetMode) -> Result<(), ModemError> {
+        mode.validate()?;
+        let req = NetModeRequest {
+            network_mode: &mode.network_mode,
+            network_band: &mode.network_band,
+            lte_band: &mode.lte_band,
+        };
+        Ok(self.control("/api/net/net-mode", req).await.context("set net mode")?)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn scan_networks(&self) -> Result<Vec<AvailableNetwork>, ModemError> {
+        const PATH: &str = "/api/net/plmn-list";
+        self.ensure_session().await?;
+        let resp = self.send(PATH, self.client.get(self.url(PATH)).timeout(PLMN_SCAN_TIMEOUT)).await?;
+        let list = self.decode::<ModemResponse<PlmnList>>(PATH, resp)?.ok().context("scan networks")?;
+        Ok(list.networks.network)
+    }
+
+    pub async fn sample_signal(&self) -> Result<(), ModemError> {
+        self.ensure_session().await?;
+        let session = self.session();
+        let signal = match self.api_get::<SignalInfo>("/api/device/signal").await {
+            Err(err) if is_session_expired(&err) => {
+                self.invalidate_session(&session);
+                return Err(err.into());
+            }
+            result => result?,
+        };
+        if let Some(rsrp) = signal.rsrp.as_deref().and_then(parse_signal_value) {
+            SignalSamples::record(&mut self.signal_samples.lock().unwrap(), rsrp);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn take_signal_samples(&self) -> Option<SignalSamples> {
+        self.signal_samples.lock().unwrap().take()
+    }
+
+    pub async fn get_raw(&self, path: &str) -> Result<String, ModemError> {
+        self.ensure_session().await?;
+        let resp = self.send(path, self.client.get(self.url(path))).await?;
+        Ok(if self.redact_identity { redact_identity_tags(&resp.text()) } else { resp.text() })
+    }
+
+    pub async fn check(&self) -> Result<(), ModemError> {
+        Ok(self.ensure_session().await?)
+    }
+
+    async fn fetch_session(&self) -> Result<SessionResponse> {
+        let session = self.get::<ModemResponse<SessionResponse>>("/api/webserver/SesTokInfo").await;
+        match session.and_then(ModemResponse::ok) {
+            Err(err) if is_unsupported(&err) => {
+                debug!("SesTokInfo unsupported, reading session from the landing page");
+                self.landing_page_session().await
+            }
+            result => result,
+        }
+    }
+
+    async fn landing_page_session(&self) -> Result<SessionResponse> {
+        let mut resp = self.send("/", self.client.get(self.url("/"))).await?;
+        if resp.status.is_redirection() {
+            let location = resp.headers.get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .filter(|location| location.starts_with('/'))
+                .context("landing page redirected to an unsupported location")?
+                .to_string();
+            resp = self.send(&location, self.client.get(self.url(&location))).await?;
+        }
+        let session = resp.headers.get("Set-Cookie")
+            .and_then(|value| value.to_str().ok())
+            .map(|cookie| cookie.split(';').next().unwrap_or_default().to_string())
+            .context("landing page did not set a session cookie")?;
+        let html = resp.text();
+        let token = csrf_token(&html).context("landing page has no csrf token")?;
+        Ok(SessionResponse { session, token })
+    }
+
+    async fn refresh_session(&self) -> Result<()> {
+        self.set_session(Some(
+            self.retry(|| self.fetch_session()).await.context("get session")?
+        ));
+        if self.credentials.is_some() {
+            self.authenticate().await?;
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn gather_statistics(&self) -> Result<Statistics, ModemError> {
+        let deadline = self.scrape_deadline.map(|deadline| Instant::now() + deadline);
+        within(deadline, self.ensure_session()).await.ok_or(ModemError::Timeout)??;
+
+        let mut session = self.session();
+        let mut result = Box::pin(self.fetch_statistics(deadline)).await;
+        if result.as_ref().is_err_and(is_session_expired) {
+            debug!("session expired, refreshing");
+            self.invalidate_session(&session);
+            within(deadline, self.ensure_session()).await.ok_or(ModemError::Timeout)??;
+            session = self.session();
+            result = Box::pin(self.fetch_statistics(deadline)).await;
+        }
+        if result.as_ref().is_err_and(is_session_expired) {
+            self.invalidate_session(&session);
+        }
+        Ok(result?)
+    }
+
+    fn track_traffic(&self, traffic: &mut TrafficStatistics) {
+        // Track on arrival so concurrent scrapes see samples in the order the modem answered them.
+        let _tracking = self.tracking.lock().unwrap();
+        self.track_connection(traffic);
+        self.track_rates(traffic);
+        self.track_lifetime(traffic);
+    }
+
+    fn track_connection(&self, traffic: &TrafficStatistics) {
+        let mut last_connect_time = self.last_connect_time.lock().unwrap();
+        if last_connect_time.is_some_and(|last| traffic.current_connect_time < last) {
+            self.reconnects.inc();
+        }
+        *last_connect_time = Some(traffic.current_connect_time);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        *self.session_start.lock().unwrap() = Some(traffic.current_connect_time)
+            .filter(|connect_time| *connect_time > 0)
+            .and_then(|connect_time| now.as_secs().checked_sub(connect_time))
+            .map(|start| start as f64);
+    }
+
+    fn track_lifetime(&self, traffic: &TrafficStatistics) {
+        let Some(lifetime) = &self.lifetime else {
+            return;
+        };
+        lifetime.observe(LifetimeTotals { upload: traffic.total_upload, download: traffic.total_download });
+        if let Err(err) = lifetime.save() {
+            warn!("failed to save lifetime traffic: {err:#}");
+        }
+    }
+
+    fn track_rates(&self, traffic: &mut TrafficStatistics) {
+        let now = Instant::now();
+        let previous = self.last_traffic.lock().unwrap()
+            .replace((now, traffic.current_upload, traffic.current_download));
+        let Some((time, upload, download)) = previous else {
+            return;
+        };
+
+        let elapsed = now.duration_since(time).as_secs_f64();
+        let rate = |current: u64, previous: u64| match current.checked_sub(previous) {
+            Some(delta) if elapsed > 0.0 => Some((delta as f64 / elapsed) as u64),
+            _ => None,
+        };
+        if traffic.current_upload_rate.is_none() {
+            traffic.current_upload_rate = rate(traffic.current_upload, upload);
+        }
+        if traffic.current_download_rate.is_none() {
+            traffic.current_download_rate = rate(traffic.current_download, download);
+        }
+    }
+
+    async fn fetch_data_switch(&self) -> Result<Option<DataSwitch>> {
+        if !self.collector_enabled("data_switch") {
+            return Ok(None);
+        }
+        match self.api_get_optional("/api/dialup/mobile-dataswitch").await? {
+            Some(data_switch) => Ok(Some(data_switch)),
+            None => self.api_get_optional("/api/dialup/connection").await,
+        }
+    }
+
+    async fn fetch_status(&self) -> Result<(Option<MonitoringStatus>, Option<ConvergedStatus>)> {
+        if !self.collector_enabled("status") {
+            return Ok((None, None));
+        }
+        match self.api_get_optional::<ConvergedStatus>("/api/monitoring/converged-status").await? {
+            Some(converged) => Ok((Some(converged.status()), Some(converged))),
+            None => Ok((self.api_get_collector("status", "/api/monitoring/status").await?, None)),
+        }
+    }
+
+    fn traffic_paths(&self) -> Vec<String> {
+        let found = self.found_traffic_path.lock().unwrap().clone();
+        let mut paths = Vec::new();
+        for path in found.into_iter().chain(self.traffic_path.clone()).chain(TRAFFIC_PATHS.map(str::to_string)) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+
+    async fn fetch_traffic(&self) -> Result<Option<TrafficStatistics>> {
+        if !self.collector_enabled("traffic") {
+            return Ok(None);
+        }
+        for path in self.traffic_paths() {
+            match self.api_get_partial(&path).await? {
+                Ok(mut traffic) => {
+                    let mut found = self.found_traffic_path.lock().unwrap();
+                    if found.as_deref() != Some(&path) {
+                        info!("traffic statistics available at {path}");
+                        *found = Some(path);
+                    }
+                    drop(found);
+                    self.track_traffic(&mut traffic);
+                    return Ok(Some(traffic));
+                }
+                Err(err) => debug!("{path} unavailable: {err:#}"),
+            }
+        }
+        warn!("traffic statistics unavailable at any known path");
+        Ok(None)
+    }
+
+    async fn fetch_antenna(&self) -> Result<Option<Antenna>> {
+        if !self.collector_enabled("antenna") {
+            return Ok(None);
+        }
+        self.api_get_optional("/api/device/antenna").await
+    }
+
+    async fn fetch_statistics(&self, deadline: Option<Instant>) -> Result<Statistics> {
+        let timed_out = AtomicBool::new(false);
+        let (
+            traffic,
+            signal,
+            device,
+            sms,
+            (status, converged),
+            month,
+            pin,
+            wlan_hosts,
+            wlan_settings,
+            plmn,
+            data_switch,
+            antenna,
+            notifications,
+            start_date,
+            net_mode,
+            wan,
+            firmware_update,
+        ) = tokio::try_join!(
+            bounded(deadline, &timed_out, self.fetch_traffic()),
+            bounded(deadline, &timed_out, self.api_get_collector("signal", "/api/device/signal")),
+            bounded(deadline, &timed_out, self.api_get_collector("device", "/api/device/information")),
+            bounded(deadline, &timed_out, self.api_get_collector("sms", "/api/sms/sms-count")),
+            bounded(deadline, &timed_out, self.fetch_status()),
+            bounded(deadline, &timed_out, self.api_get_collector("month", "/api/monitoring/month_statistics")),
+            bounded(deadline, &timed_out, self.api_get_collector("pin", "/api/pin/status")),
+            bounded(deadline, &timed_out, self.api_get_collector("wlan_hosts", "/api/wlan/host-list")),
+            bounded(deadline, &timed_out, self.api_get_collector("wlan_settings", "/api/wlan/basic-settings")),
+            bounded(deadline, &timed_out, self.api_get_collector("plmn", "/api/net/current-plmn")),
+            bounded(deadline, &timed_out, self.fetch_data_switch()),
+            bounded(deadline, &timed_out, self.fetch_antenna()),
+            bounded(deadline, &timed_out, self.api_get_collector("notifications", "/api/monitoring/check-notifications")),
+            bounded(deadline, &timed_out, self.api_get_collector("start_date", "/api/monitoring/start_date")),
+            bounded(deadline, &timed_out, self.api_get_collector("net_mode", "/api/net/net-mode")),
+            bounded(deadline, &timed_out, self.api_get_collector("wan", "/api/dialup/connection")),
+            bounded(deadline, &timed_out, self.api_get_collector("firmware_update", "/api/online-update/check-new-version")),
+        )?;
+        let device = device.map(|device: DeviceInformation| {
+            if self.redact_identity { device.redacted() } else { device }
+        });
+        let antenna = antenna.or_else(|| {
+            let signal: &SignalInfo = signal.as_ref()?;
+            Some(Antenna { antennatype: Some(signal.antennatype.clone()?), antenna_settings: None })
+        });
+        Ok(Statistics {
+            traffic,
+            signal,
+            device,
+            sms,
+            status,
+            converged,
+            month,
+            pin,
+            wlan_hosts,
+            wlan_settings,
+            plmn,
+            data_switch,
+            antenna,
+            notifications,
+            start_date,
+            net_mode,
+            wan,
+            firmware_update,
+            timed_out: timed_out.into_inner(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SessionError;
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("session error")
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{bail, Context, Result};
+
+pub const PROTOBUF_CONTENT_TYPE: &str =
+    "application/vnd.google.protobuf; proto=io.prometheus.client.MetricFamily; encoding=delimited";
+
+const COUNTER: u64 = 0;
+const GAUGE: u64 = 1;
+const UNTYPED: u64 = 3;
+const HISTOGRAM: u64 = 4;
+
+type Labels = Vec<(String, String)>;
+
+struct Sample {
+    name: String,
+    labels: Labels,
+    value: f64,
+}
+
+struct Family {
+    name: String,
+    help: String,
+    metric_type: String,
+    unit: Option<String>,
+    samples: Vec<Sample>,
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn parse_labels(labels: &str) -> Result<(Labels, &str)> {
+    let mut parsed = Vec::new();
+    let mut rest = labels;
+    loop {
+        if let Some(rest) = rest.strip_prefix('}') {
+            return Ok((parsed, rest));
+        }
+        let (name, value) = rest.split_once("=\"").with_context(|| format!("malformed labels: {labels:?}"))?;
+        let mut escaped = false;
+        let end = value.char_indices()
+            .find(|&(_, c)| {
+                let quote = !escaped && c == '"';
+                escaped = !escaped && c == '\\';
+                quote
+            })
+            .map(|(end, _)| end)
+            .with_context(|| format!("unterminated label value: {labels:?}"))?;
+        parsed.push((name.to_string(), unescape(&value[..end])));
+        rest = value[end + 1..].strip_prefix(',').unwrap_or(&value[end + 1..]);
+    }
+}
+
+fn parse_value(value: &str) -> Result<f64> {
+    match value {
+        "+Inf" => Ok(f64::INFINITY),
+        "-Inf" => Ok(f64::NEG_INFINITY),
+        "NaN" => Ok(f64::NAN),
+        _ => value.parse().with_context(|| format!("invalid sample value: {value:?}")),
+    }
+}
+
+fn parse(exposition: &str) -> Result<Vec<Family>> {
+    let mut families: Vec<Family> = Vec::new();
+    for line in exposition.lines().filter(|line| !line.is_empty() && *line != "# EOF") {
+        if let Some(descriptor) = line.strip_prefix("# ") {
+            let mut parts = descriptor.splitn(3, ' ');
+            let (kind, name, text) = (parts.next(), parts.next(), parts.next().unwrap_or_default());
+            let name = name.with_context(|| format!("malformed descriptor: {line:?}"))?;
+            if families.last().is_none_or(|family| family.name != name) {
+                families.push(Family {
+                    name: name.to_string(),
+                    help: String::new(),
+                    metric_type: "unknown".to_string(),
+                    unit: None,
+                    samples: Vec::new(),
+                });
+            }
+            let family = families.last_mut().unwrap();
+            match kind {
+                Some("HELP") => family.help = unescape(text),
+                Some("TYPE") => family.metric_type = text.to_string(),
+                Some("UNIT") => family.unit = Some(text.to_string()).filter(|unit| !unit.is_empty()),
+                _ => {}
+            }
+            continue;
+        }
+
+        let family = families.last_mut().with_context(|| format!("sample without family: {line:?}"))?;
+        let name_end = line.find(['{', ' ']).with_context(|| format!("malformed sample: {line:?}"))?;
+        let (name, rest) = line.split_at(name_end);
+        let (labels, rest) = match rest.strip_prefix('{') {
+            Some(labels) => parse_labels(labels)?,
+            None => (Vec::new(), rest),
+        };
+        let value = rest.split_whitespace().next().with_context(|| format!("sample without value: {line:?}"))?;
+        family.samples.push(Sample { name: name.to_string(), labels, value: parse_value(value)? });
+    }
+    Ok(families)
+}
+
+fn varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn uint_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    varint(buf, field << 3);
+    varint(buf, value);
+}
+
+fn double_field(buf: &mut Vec<u8>, field: u64, value: f64) {
+    varint(buf, field << 3 | 1);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn bytes_field(buf: &mut Vec<u8>, field: u64, value: &[u8]) {
+    varint(buf, field << 3 | 2);
+    varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+fn metric(labels: &Labels, field: u64, value: &[u8]) -> Vec<u8> {
+    let mut metric = Vec::new();
+    for (name, value) in labels {
+        let mut pair = Vec::new();
+        bytes_field(&mut pair, 1, name.as_bytes());
+        bytes_field(&mut pair, 2, value.as_bytes());
+        bytes_field(&mut metric, 1, &pair);
+    }
+    bytes_field(&mut metric, field, value);
+    metric
+}
+
+fn value_metric(sample: &Sample, field: u64) -> Vec<u8> {
+    let mut value = Vec::new();
+    double_field(&mut value, 1, sample.value);
+    metric(&sample.labels, field, &value)
+}
+
+fn histogram_metrics(family: &Family) -> Result<Vec<Vec<u8>>> {
+    let mut histograms: Vec<(Labels, Vec<u8>, Vec<u8>)> = Vec::new();
+    for sample in &family.samples {
+        let suffix = sample.name.strip_prefix(&family.name).unwrap_or_default();
+        let mut labels = sample.labels.clone();
+        let le = labels.iter().position(|(name, _)| name == "le").map(|index| labels.remove(index).1);
+        let index = match histograms.iter().position(|(existing, _, _)| *existing == labels) {
+            Some(index) => index,
+            None => {
+                histograms.push((labels, Vec::new(), Vec::new()));
+                histograms.len() - 1
+            }
+        };
+        let (_, fields, buckets) = &mut histograms[index];
+        match (suffix, le) {
+            ("_count", _) => uint_field(fields, 1, sample.value as u64),
+            ("_sum", _) => double_field(fields, 2, sample.value),
+            ("_bucket", Some(le)) => {
+                let mut bucket = Vec::new();
+                uint_field(&mut bucket, 1, sample.value as u64);
+                double_field(&mut bucket, 2, parse_value(&le)?);
+                bytes_field(buckets, 3, &bucket);
+            }
+            ("_created", _) => {}
+            _ => bail!("unexpected histogram sample {}", sample.name),
+        }
+    }
+    Ok(histograms.into_iter()
+        .map(|(labels, mut fields, buckets)| {
+            fields.extend(buckets);
+            metric(&labels, 7, &fields)
+        })
+        .collect())
+}
+
+fn encode_family(buf: &mut Vec<u8>, family: &Family) -> Result<()> {
+    let values = |suffix: &str, field: u64| -> Vec<Vec<u8>> {
+        let name = format!("{}{suffix}", family.name);
+        family.samples.iter()
+            .filter(|sample| sample.name == name)
+            .map(|sample| value_metric(sample, field))
+            .collect()
+    };
+    let (name, metric_type, metrics) = match family.metric_type.as_str() {
+        "counter" => (format!("{}_total", family.name), COUNTER, values("_total", 3)),
+        "gauge" | "stateset" => (family.name.clone(), GAUGE, values("", 2)),
+        "info" => (format!("{}_info", family.name), GAUGE, values("_info", 2)),
+        "histogram" => (family.name.clone(), HISTOGRAM, histogram_metrics(family)?),
+        "unknown" => (family.name.clone(), UNTYPED, values("", 5)),
+        metric_type => bail!("unsupported metric type {metric_type} for {}", family.name),
+    };
+    if metrics.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = Vec::new();
+    bytes_field(&mut message, 1, name.as_bytes());
+    bytes_field(&mut message, 2, family.help.as_bytes());
+    uint_field(&mut message, 3, metric_type);
+    for metric in &metrics {
+        bytes_field(&mut message, 4, metric);
+    }
+    if let Some(unit) = &family.unit {
+        bytes_field(&mut message, 5, unit.as_bytes());
+    }
+    varint(buf, message.len() as u64);
+    buf.extend(message);
+    Ok(())
+}
+
+/// Converts an OpenMetrics text exposition into length-delimited `io.prometheus.client.MetricFamily` messages.
+pub fn encode(exposition: &str) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    for family in parse(exposition)? {
+        encode_family(&mut buf, &family)?;
+    }
+    Ok(buf)
+}
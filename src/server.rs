@@ -0,0 +1,730 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Context, Result};
+use base64::prelude::*;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use tokio::net::UnixListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::UnixListenerStream;
+use tracing::{debug, info, warn};
+use warp::{reply, Filter, Rejection, Reply};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::hyper::body::Bytes;
+use warp::http::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
+
+use crate::metrics::{encode_modem_collector, gather_json, gather_metrics, ScrapeMetrics, StatisticsCache};
+use crate::protobuf::{self, PROTOBUF_CONTENT_TYPE};
+use crate::modem::{normalize_base_url, AvailableNetworks, Credentials, LifetimeTraffic, Modem, NetMode, Sms};
+
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const TEXT_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+const UNIX_SOCKET_MODE: u32 = 0o660;
+
+const MAX_TARGETS: usize = 64;
+
+const MAX_ADMIN_BODY: u64 = 4096;
+
+pub const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+pub const DEFAULT_ALLOWED_NETWORKS: [&str; 4] = ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = anyhow::Error;
+
+    fn from_str(network: &str) -> Result<Self> {
+        let network = network.trim();
+        let (address, prefix) = network.split_once('/').unwrap_or((network, ""));
+        let address = IpAddr::from_str(address).with_context(|| format!("invalid network: {network:?}"))?;
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            "" => max,
+            prefix => prefix.parse().ok().filter(|prefix| *prefix <= max)
+                .with_context(|| format!("invalid network prefix: {network:?}"))?,
+        };
+        Ok(Self { address, prefix })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(address: &str) -> Result<Self> {
+        if let Some(path) = address.strip_prefix("unix:") {
+            ensure!(!path.is_empty(), "invalid unix socket path: {address:?}");
+            return Ok(Self::Unix(path.into()));
+        }
+        let address = match address.strip_prefix(':') {
+            Some(port) => format!("0.0.0.0:{port}"),
+            None => address.to_string(),
+        };
+        let address = SocketAddr::from_str(&address).with_context(|| format!("invalid listen address: {address:?}"))?;
+        Ok(Self::Tcp(address))
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(address) => write!(f, "{address}"),
+            ListenAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+fn bind_unix(path: &Path) -> Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) => {
+            ensure!(metadata.file_type().is_socket(), "refusing to replace non-socket file {}", path.display());
+            std::fs::remove_file(path).with_context(|| format!("remove stale socket {}", path.display()))?;
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(anyhow::Error::new(err).context(format!("inspect {}", path.display()))),
+    }
+    let listener = UnixListener::bind(path).with_context(|| format!("bind {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(UNIX_SOCKET_MODE))
+        .with_context(|| format!("set permissions on {}", path.display()))?;
+    Ok(listener)
+}
+
+pub async fn resolve_target(target: &str, networks: &[IpNetwork]) -> Result<IpAddr> {
+    let host = target.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(target);
+    let addresses = match IpAddr::from_str(host) {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            ensure!(
+                !host.is_empty() && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-'),
+                "invalid target: {target:?}",
+            );
+            tokio::net::lookup_host((host, 80)).await
+                .with_context(|| format!("resolve target {target}"))?
+                .map(|address| address.ip())
+                .collect()
+        }
+    };
+    ensure!(
+        !addresses.is_empty() && addresses.iter().all(|ip| networks.iter().any(|network| network.contains(*ip))),
+        "target not allowed: {target}",
+    );
+    Ok(addresses[0])
+}
+
+struct Target {
+    modem: Modem,
+    scrape: ScrapeMetrics,
+    cache: StatisticsCache,
+}
+
+impl Target {
+    async fn scrape(&self, accept: Option<&str>) -> reply::Response {
+        let data = gather_metrics(&self.modem, &self.scrape, &self.cache).await;
+        match data.and_then(|data| match negotiate(accept) {
+            PROTOBUF_CONTENT_TYPE => Ok((protobuf::encode(&data)?, PROTOBUF_CONTENT_TYPE)),
+            content_type => Ok((data.into_bytes(), content_type)),
+        }) {
+            Ok((data, content_type)) => reply::with_header(data, CONTENT_TYPE, content_type).into_response(),
+            Err(err) => reply::with_status(format!("{err:?}"), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        }
+    }
+
+    async fn scrape_json(&self) -> reply::Response {
+        match gather_json(&self.modem, &self.scrape, &self.cache).await {
+            Ok(data) => reply::with_header(data, CONTENT_TYPE, "application/json").into_response(),
+            Err(err) => reply::with_status(format!("{err:?}"), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        }
+    }
+
+    fn ready(&self) -> reply::WithStatus<String> {
+        if self.scrape.ever_succeeded() {
+            reply::with_status("ok".to_string(), StatusCode::OK)
+        } else {
+            reply::with_status("waiting for the first successful scrape".to_string(), StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ModemConfig {
+    pub credentials: Option<Credentials>,
+    pub timeout: Duration,
+    pub insecure_tls: bool,
+    pub retries: u32,
+    pub cache_ttl: Option<Duration>,
+    pub collectors: Option<Vec<String>>,
+    pub redact_identity: bool,
+    pub metric_prefix: String,
+    pub instance_label: Option<String>,
+    pub instance_alias: Option<String>,
+    pub scrape_deadline: Option<Duration>,
+    pub state_file: Option<PathBuf>,
+    pub user_agent: Option<String>,
+    pub accept_language: Option<String>,
+    pub traffic_path: Option<String>,
+}
+
+impl ModemConfig {
+    pub fn build(&self, address: impl Into<String>) -> Result<Modem> {
+        let address = normalize_base_url(&address.into());
+        let host = address.split_once("://").map_or(address.as_str(), |(_, rest)| rest);
+        let host = host.split('/').next().unwrap_or(host);
+        let labels = self.instance_label.iter()
+            .map(|name| (name.clone(), self.instance_alias.clone().unwrap_or_else(|| host.to_string())))
+            .collect();
+        Ok(Modem::new(address, self.timeout, self.insecure_tls)?
+            .with_credentials(self.credentials.clone())
+            .with_user_agent(self.user_agent.clone())
+            .with_accept_language(self.accept_language.clone())
+            .with_traffic_path(self.traffic_path.clone())
+            .with_retries(self.retries)
+            .with_collectors(self.collectors.clone())
+            .with_redact_identity(self.redact_identity)
+            .with_metric_prefix(self.metric_prefix.clone())
+            .with_labels(labels)
+            .with_scrape_deadline(self.scrape_deadline)
+            .with_lifetime_traffic(self.state_file.as_ref().map(LifetimeTraffic::load).transpose()?))
+    }
+
+    fn target(&self, address: impl Into<String>) -> Result<Target> {
+        Ok(Target {
+            modem: self.build(address)?,
+            scrape: ScrapeMetrics::new(),
+            cache: StatisticsCache::new(self.cache_ttl),
+        })
+    }
+}
+
+pub struct Exporter {
+    default: Arc<Target>,
+    targets: Mutex<HashMap<String, (Arc<Target>, Instant)>>,
+    allowed_targets: Option<Vec<String>>,
+    allowed_networks: Vec<IpNetwork>,
+    admin_token: Option<String>,
+    metrics_credentials: Option<Credentials>,
+    debug_endpoints: bool,
+    signal_sample_interval: Option<Duration>,
+    metrics_path: String,
+    config: ModemConfig,
+}
+
+impl Exporter {
+    pub fn new(address: impl Into<String>, config: ModemConfig, allowed_targets: Option<Vec<String>>) -> Result<Exporter> {
+        Ok(Self {
+            default: Arc::new(config.target(address)?),
+            targets: Mutex::default(),
+            allowed_targets,
+            allowed_networks: DEFAULT_ALLOWED_NETWORKS.iter().map(|network| network.parse()).collect::<Result<_>>()?,
+            admin_token: None,
+            metrics_credentials: None,
+            debug_endpoints: false,
+            signal_sample_interval: None,
+            metrics_path: DEFAULT_METRICS_PATH.to_string(),
+            config: ModemConfig { state_file: None, instance_alias: None, ..config },
+        })
+    }
+
+    pub fn with_admin_token(mut self, admin_token: Option<String>) -> Self {
+        self.admin_token = admin_token;
+        self
+    }
+
+    pub fn with_allowed_networks(mut self, allowed_networks: Option<Vec<IpNetwork>>) -> Self {
+        if let Some(allowed_networks) = allowed_networks {
+            self.allowed_networks = allowed_networks;
+        }
+        self
+    }
+
+    pub fn with_metrics_credentials(mut self, metrics_credentials: Option<Credentials>) -> Self {
+        self.metrics_credentials = metrics_credentials;
+        self
+    }
+
+    pub fn with_debug_endpoints(mut self, debug_endpoints: bool) -> Self {
+        self.debug_endpoints = debug_endpoints;
+        self
+    }
+
+    pub fn with_signal_sample_interval(mut self, signal_sample_interval: Option<Duration>) -> Self {
+        self.signal_sample_interval = signal_sample_interval;
+        self
+    }
+
+    pub fn with_metrics_path(mut self, metrics_path: impl Into<String>) -> Self {
+        self.metrics_path = metrics_path.into();
+        self
+    }
+
+    async fn sample_signal(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = self.default.modem.sample_signal().await {
+                debug!("signal sample failed: {err:#}");
+            }
+        }
+    }
+
+    fn authorize_metrics(&self, authorization: Option<&str>) -> bool {
+        let Some(credentials) = &self.metrics_credentials else {
+            return true;
+        };
+        let Some(decoded) = authorization
+            .and_then(|value| value.strip_prefix("Basic "))
+            .and_then(|value| BASE64_STANDARD.decode(value.trim()).ok()) else {
+            return false;
+        };
+        let (username, password) = match decoded.iter().position(|&byte| byte == b':') {
+            Some(index) => (&decoded[..index], &decoded[index + 1..]),
+            None => return false,
+        };
+        constant_time_eq(username, credentials.username.as_bytes())
+            & constant_time_eq(password, credentials.password.as_bytes())
+    }
+
+    fn authorize(&self, authorization: Option<&str>) -> Result<(), reply::WithStatus<String>> {
+        let Some(admin_token) = &self.admin_token else {
+            return Err(reply::with_status("not found".to_string(), StatusCode::NOT_FOUND));
+        };
+        let token = authorization.and_then(|value| value.strip_prefix("Bearer "));
+        if !token.is_some_and(|token| constant_time_eq(token.as_bytes(), admin_token.as_bytes())) {
+            return Err(reply::with_status("unauthorized".to_string(), StatusCode::UNAUTHORIZED));
+        }
+        Ok(())
+    }
+
+    async fn reboot(&self, authorization: Option<String>) -> reply::WithStatus<String> {
+        if let Err(reply) = self.authorize(authorization.as_deref()) {
+            return reply;
+        }
+
+        match self.default.modem.reboot().await {
+            Ok(()) => {
+                info!("reboot requested");
+                reply::with_status("ok".to_string(), StatusCode::OK)
+            }
+            Err(err) => {
+                warn!("reboot failed: {err:#}");
+                reply::with_status(format!("{err:#}"), StatusCode::BAD_GATEWAY)
+            }
+        }
+    }
+
+    async fn send_sms(&self, authorization: Option<String>, body: Bytes) -> reply::WithStatus<String> {
+        if let Err(reply) = self.authorize(authorization.as_deref()) {
+            return reply;
+        }
+        let sms = match decode_body::<Sms>(&body) {
+            Ok(sms) => sms,
+            Err(reply) => return reply,
+        };
+        if let Err(err) = sms.validate() {
+            return reply::with_status(format!("{err:#}"), StatusCode::BAD_REQUEST);
+        }
+
+        match self.default.modem.send_sms(&sms).await {
+            Ok(()) => {
+                info!("sms sent");
+                debug!(number = sms.number, "sms recipient");
+                reply::with_status("ok".to_string(), StatusCode::OK)
+            }
+            Err(err) => {
+                warn!("send sms failed: {err:#}");
+                reply::with_status(format!("{err:#}"), StatusCode::BAD_GATEWAY)
+            }
+        }
+    }
+
+    async fn scan(&self, authorization: Option<String>) -> reply::WithStatus<String> {
+        if let Err(reply) = self.authorize(authorization.as_deref()) {
+            return reply;
+        }
+
+        let modem = &self.default.modem;
+        let result = modem.scan_networks().await
+            .map_err(anyhow::Error::from)
+            .and_then(|network| encode_modem_collector(modem, Box::new(AvailableNetworks { network })));
+        match result {
+            Ok(data) => {
+                info!("network scan finished");
+                reply::with_status(data, StatusCode::OK)
+            }
+            Err(err) => {
+                warn!("network scan failed: {err:#}");
+                reply::with_status(format!("{err:#}"), StatusCode::BAD_GATEWAY)
+            }
+        }
+    }
+
+    async fn raw(&self, authorization: Option<String>, path: &str) -> reply::Response {
+        if !self.debug_endpoints {
+            return reply::with_status("not found".to_string(), StatusCode::NOT_FOUND).into_response();
+        }
+        if !self.authorize_metrics(authorization.as_deref()) {
+            return unauthorized();
+        }
+        if !path.starts_with("/api/") || path.contains("..") {
+            return reply::with_status(format!("invalid path: {path}"), StatusCode::BAD_REQUEST).into_response();
+        }
+
+        match self.default.modem.get_raw(path).await {
+            Ok(body) => reply::with_status(body, StatusCode::OK).into_response(),
+            Err(err) => reply::with_status(format!("{err:#}"), StatusCode::BAD_GATEWAY).into_response(),
+        }
+    }
+
+    async fn logout(&self) {
+        let targets = self.targets.lock().await;
+        for target in std::iter::once(&self.default).chain(targets.values().map(|(target, _)| target)) {
+            if let Err(err) = target.modem.logout().await {
+                warn!("logout failed: {err:#}");
+            }
+        }
+    }
+
+    async fn set_net_mode(&self, authorization: Option<String>, body: Bytes) -> reply::WithStatus<String> {
+        if let Err(reply) = self.authorize(authorization.as_deref()) {
+            return reply;
+        }
+        let mode = match decode_body::<NetMode>(&body) {
+            Ok(mode) => mode,
+            Err(reply) => return reply,
+        };
+        if let Err(err) = mode.validate() {
+            return reply::with_status(format!("{err:#}"), StatusCode::BAD_REQUEST);
+        }
+
+        match self.default.modem.set_net_mode(&mode).await {
+            Ok(()) => {
+                info!(?mode, "net mode changed");
+                reply::with_status("ok".to_string(), StatusCode::OK)
+            }
+            Err(err) => {
+                warn!("set net mode failed: {err:#}");
+                reply::with_status(format!("{err:#}"), StatusCode::BAD_GATEWAY)
+            }
+        }
+    }
+
+    async fn resolve_target(&self, target: &str) -> Result<String> {
+        match &self.allowed_targets {
+            Some(allowed) => {
+                ensure!(allowed.iter().any(|allowed| allowed == target), "target not allowed: {target}");
+                Ok(target.to_string())
+            }
+            None => Ok(resolve_target(target, &self.allowed_networks).await?.to_string()),
+        }
+    }
+
+    async fn target(&self, target: Option<&str>) -> Result<Arc<Target>, reply::WithStatus<String>> {
+        let Some(target) = target else {
+            return Ok(self.default.clone());
+        };
+        let address = self.resolve_target(target).await
+            .map_err(|err| reply::with_status(format!("{err:#}"), StatusCode::BAD_REQUEST))?;
+
+        let mut targets = self.targets.lock().await;
+        if let Some((existing, last_used)) = targets.get_mut(&address) {
+            *last_used = Instant::now();
+            return Ok(existing.clone());
+        }
+        // Only explicitly configured targets get the modem credentials; any host on the allowed
+        // networks can be a dynamic target and would otherwise receive the login proof.
+        let created = match &self.allowed_targets {
+            Some(_) => self.config.target(address.as_str()),
+            None => ModemConfig { credentials: None, ..self.config.clone() }.target(address.as_str()),
+        };
+        let created = created
+            .map_err(|err| reply::with_status(format!("{err:?}"), StatusCode::INTERNAL_SERVER_ERROR))?;
+        let created = Arc::new(created);
+        if targets.len() >= MAX_TARGETS {
+            let oldest = targets.iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(address, _)| address.clone());
+            if let Some((evicted, _)) = oldest.and_then(|oldest| targets.remove(&oldest)) {
+                debug!("evicting idle target");
+                tokio::spawn(async move {
+                    if let Err(err) = evicted.modem.logout().await {
+                        warn!("logout failed: {err:#}");
+                    }
+                });
+            }
+        }
+        targets.insert(address, (created.clone(), Instant::now()));
+        Ok(created)
+    }
+}
+
+async fn recover(rejection: Rejection) -> Result<reply::WithStatus<String>, Infallible> {
+    let status = if rejection.is_not_found() {
+        StatusCode::NOT_FOUND
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        StatusCode::METHOD_NOT_ALLOWED
+    } else if rejection.find::<warp::reject::InvalidQuery>().is_some() {
+        StatusCode::BAD_REQUEST
+    } else if rejection.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    } else if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        StatusCode::PAYLOAD_TOO_LARGE
+    } else if rejection.find::<warp::reject::LengthRequired>().is_some() {
+        StatusCode::LENGTH_REQUIRED
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let message = status.canonical_reason().unwrap_or_default().to_lowercase();
+    Ok(reply::with_status(message, status))
+}
+
+fn negotiate(accept: Option<&str>) -> &'static str {
+    let mut best = (TEXT_CONTENT_TYPE, 0.0);
+    for range in accept.unwrap_or_default().split(',') {
+        let mut params = range.split(';').map(str::trim);
+        let media_type = params.next().unwrap_or_default().to_ascii_lowercase();
+        let (mut quality, mut proto, mut encoding) = (1.0, None, None);
+        for (name, value) in params.filter_map(|param| param.split_once('=')) {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "q" => quality = value.trim().parse().unwrap_or(0.0),
+                "proto" => proto = Some(value.trim()),
+                "encoding" => encoding = Some(value.trim()),
+                _ => {}
+            }
+        }
+        let content_type = match media_type.as_str() {
+            "application/vnd.google.protobuf"
+                if proto == Some("io.prometheus.client.MetricFamily") && encoding.is_none_or(|encoding| encoding == "delimited") => {
+                PROTOBUF_CONTENT_TYPE
+            }
+            "application/openmetrics-text" => OPENMETRICS_CONTENT_TYPE,
+            "text/plain" | "*/*" => TEXT_CONTENT_TYPE,
+            _ => continue,
+        };
+        if quality > best.1 {
+            best = (content_type, quality);
+        }
+    }
+    best.0
+}
+
+fn decode_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, reply::WithStatus<String>> {
+    serde_json::from_slice(body)
+        .map_err(|err| reply::with_status(format!("invalid body: {err}"), StatusCode::BAD_REQUEST))
+}
+
+fn unauthorized() -> reply::Response {
+    let reply = reply::with_status("unauthorized".to_string(), StatusCode::UNAUTHORIZED);
+    reply::with_header(reply, WWW_AUTHENTICATE, "Basic realm=\"modem-exporter\"").into_response()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn path_filter(path: &str) -> BoxedFilter<()> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(warp::any().boxed(), |filter, segment| filter.and(warp::path(segment.to_string())).boxed())
+        .and(warp::path::end())
+        .boxed()
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricsQuery {
+    target: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawQuery {
+    path: String,
+}
+
+pub fn routes(exporter: Arc<Exporter>) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone {
+    let index = format!("<a href={}>Metrics</a>", exporter.metrics_path);
+    let index_route = warp::path::end().map(move || reply::html(index.clone()));
+
+    let healthz_route = warp::path!("healthz").map(|| "ok");
+
+    let readyz_route = warp::path!("readyz").map({
+        let exporter = exporter.clone();
+        move || exporter.default.ready()
+    });
+
+    let reboot_route = warp::path!("admin" / "reboot")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("authorization"))
+        .then({
+            let exporter = exporter.clone();
+            move |authorization: Option<String>| {
+                let exporter = exporter.clone();
+                async move { exporter.reboot(authorization).await }
+            }
+        });
+
+    let net_mode_route = warp::path!("admin" / "net-mode")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::content_length_limit(MAX_ADMIN_BODY))
+        .and(warp::body::bytes())
+        .then({
+            let exporter = exporter.clone();
+            move |authorization: Option<String>, body: Bytes| {
+                let exporter = exporter.clone();
+                async move { exporter.set_net_mode(authorization, body).await }
+            }
+        });
+
+    let send_sms_route = warp::path!("admin" / "send-sms")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::content_length_limit(MAX_ADMIN_BODY))
+        .and(warp::body::bytes())
+        .then({
+            let exporter = exporter.clone();
+            move |authorization: Option<String>, body: Bytes| {
+                let exporter = exporter.clone();
+                async move { exporter.send_sms(authorization, body).await }
+            }
+        });
+
+    let scan_route = warp::path!("admin" / "scan")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("authorization"))
+        .then({
+            let exporter = exporter.clone();
+            move |authorization: Option<String>| {
+                let exporter = exporter.clone();
+                async move { exporter.scan(authorization).await }
+            }
+        });
+
+    let raw_route = warp::path!("debug" / "raw")
+        .and(warp::query::<RawQuery>())
+        .and(warp::header::optional::<String>("authorization"))
+        .then({
+            let exporter = exporter.clone();
+            move |query: RawQuery, authorization: Option<String>| {
+                let exporter = exporter.clone();
+                async move { exporter.raw(authorization, &query.path).await }
+            }
+        });
+
+    let json_route = path_filter(&format!("{}.json", exporter.metrics_path))
+        .and(warp::query::<MetricsQuery>())
+        .and(warp::header::optional::<String>("authorization"))
+        .then({
+            let exporter = exporter.clone();
+            move |query: MetricsQuery, authorization: Option<String>| {
+                let exporter = exporter.clone();
+                async move {
+                    if !exporter.authorize_metrics(authorization.as_deref()) {
+                        return unauthorized();
+                    }
+                    match exporter.target(query.target.as_deref()).await {
+                        Ok(target) => target.scrape_json().await,
+                        Err(reply) => reply.into_response(),
+                    }
+                }
+            }
+        });
+
+    let metrics_route = path_filter(&exporter.metrics_path)
+        .and(warp::query::<MetricsQuery>())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("accept"))
+        .then(move |query: MetricsQuery, authorization: Option<String>, accept: Option<String>| {
+            let exporter = exporter.clone();
+            async move {
+                if !exporter.authorize_metrics(authorization.as_deref()) {
+                    return unauthorized();
+                }
+                match exporter.target(query.target.as_deref()).await {
+                    Ok(target) => target.scrape(accept.as_deref()).await,
+                    Err(reply) => reply.into_response(),
+                }
+            }
+        });
+
+    metrics_route.or(json_route).or(index_route).or(healthz_route).or(readyz_route)
+        .or(reboot_route).or(net_mode_route).or(send_sms_route).or(scan_route).or(raw_route)
+        .recover(recover)
+}
+
+pub async fn run(exporter: Exporter, listen_address: ListenAddress) -> Result<()> {
+    let exporter = Arc::new(exporter);
+
+    if let Some(interval) = exporter.signal_sample_interval {
+        let exporter = exporter.clone();
+        tokio::spawn(async move { exporter.sample_signal(interval).await });
+    }
+
+    let routes = routes(exporter.clone());
+    match listen_address {
+        ListenAddress::Tcp(listen_address) => {
+            let (listen_address, server) = warp::serve(routes)
+                .try_bind_with_graceful_shutdown(listen_address, shutdown_signal())
+                .with_context(|| format!("bind {listen_address}"))?;
+            info!("listening on {listen_address}");
+            server.await;
+        }
+        ListenAddress::Unix(path) => {
+            let listener = bind_unix(&path)?;
+            info!("listening on unix:{}", path.display());
+            warp::serve(routes)
+                .serve_incoming_with_graceful_shutdown(UnixListenerStream::new(listener), shutdown_signal())
+                .await;
+            if let Err(err) = std::fs::remove_file(&path) {
+                warn!("remove socket {} failed: {err}", path.display());
+            }
+        }
+    }
+    exporter.logout().await;
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("install SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+    info!("shutting down");
+}
@@ -0,0 +1,55 @@
+use std::process::Command;
+
+fn check(env: &[(&str, &str)], args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_modem-exporter"))
+        .env_clear()
+        .envs(env.iter().copied())
+        .args(["--check", "--timeout", "0.2", "--retries", "0", "--log-level", "off"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn address_flag_overrides_env_overrides_default() {
+    let default = check(&[], &[]);
+    assert!(default.contains("http://192.168.8.1/api/webserver/SesTokInfo"), "{default}");
+
+    let env = check(&[("MODEM_ADDRESS", "http://127.0.0.1:1")], &[]);
+    assert!(env.contains("http://127.0.0.1:1/api/webserver/SesTokInfo"), "{env}");
+
+    let flag = check(&[("MODEM_ADDRESS", "http://127.0.0.1:1")], &["--address", "http://127.0.0.1:2"]);
+    assert!(flag.contains("http://127.0.0.1:2/api/webserver/SesTokInfo"), "{flag}");
+}
+
+#[test]
+fn help_exits_successfully() {
+    let output = Command::new(env!("CARGO_BIN_EXE_modem-exporter")).arg("--help").output().unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("--address"));
+}
+
+#[test]
+fn invalid_metric_prefix_is_rejected() {
+    for prefix in ["", "hi-link_", "5g_", "modem prefix"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_modem-exporter"))
+            .env_clear()
+            .args(["--check", "--metric-prefix", prefix])
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success(), "{prefix:?}");
+        assert!(String::from_utf8(output.stderr).unwrap().contains("invalid metric prefix"), "{prefix:?}");
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_modem-exporter"))
+        .env_clear()
+        .env("METRIC_PREFIX", "hi-link_")
+        .arg("--check")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(output.stderr).unwrap().contains("invalid metric prefix"));
+}
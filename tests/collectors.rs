@@ -0,0 +1,86 @@
+use modem_exporter::metrics::{encode_collector, parse_signal_value};
+use modem_exporter::modem::{SignalInfo, TrafficStatistics};
+
+fn traffic() -> TrafficStatistics {
+    TrafficStatistics {
+        current_upload: 1000,
+        current_download: 2000,
+        current_connect_time: 120,
+        total_upload: 30000,
+        total_download: 40000,
+        total_connect_time: 3600,
+        current_upload_rate: Some(10),
+        current_download_rate: None,
+    }
+}
+
+fn signal() -> SignalInfo {
+    SignalInfo {
+        rssi: Some("-51dBm".to_string()),
+        rsrp: Some("-95dBm".to_string()),
+        rsrq: Some("-10.5dB".to_string()),
+        sinr: Some("15dB".to_string()),
+        ecio: None,
+        band: Some("3".to_string()),
+        dlbandwidth: None,
+        ulbandwidth: None,
+        ca: None,
+        antennatype: None,
+        temperature: None,
+        cell_id: None,
+        pci: None,
+        tac: None,
+        lac: None,
+    }
+}
+
+#[test]
+fn encode_collector_renders_traffic_statistics() {
+    let output = encode_collector(Box::new(traffic())).unwrap();
+
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 1000.0\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"download\"} 2000.0\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"download\"} 40000\n"), "{output}");
+    assert!(output.contains("modem_session_connect_duration_seconds 120\n"), "{output}");
+    assert!(output.contains("modem_connect_duration_seconds_total 3600\n"), "{output}");
+    assert!(output.contains("modem_current_upload_rate_bytes_per_second 10\n"), "{output}");
+    assert!(!output.contains("modem_current_download_rate"), "{output}");
+    assert!(output.ends_with("# EOF\n"), "{output}");
+}
+
+#[test]
+fn encode_collector_renders_signal_info() {
+    let output = encode_collector(Box::new(signal())).unwrap();
+
+    assert!(output.contains("modem_signal_rssi_dbm -51.0\n"), "{output}");
+    assert!(output.contains("modem_signal_rsrp_dbm -95.0\n"), "{output}");
+    assert!(output.contains("modem_signal_rsrq_db -10.5\n"), "{output}");
+    assert!(output.contains("modem_signal_sinr_db 15.0\n"), "{output}");
+    assert!(output.contains("modem_connection_quality 1\n"), "{output}");
+    assert!(output.contains("modem_lte_band_info{band=\"3\"} 1\n"), "{output}");
+    assert!(!output.contains("modem_signal_ecio"), "{output}");
+}
+
+#[test]
+fn parse_signal_value_handles_units_and_comparators() {
+    let cases = [
+        ("-95dBm", Some(-95.0)),
+        (">=-51dBm", Some(-51.0)),
+        ("<=-113dBm", Some(-113.0)),
+        ("10.5dB", Some(10.5)),
+        ("-3.5dB", Some(-3.5)),
+        (" -7 dB ", Some(-7.0)),
+        ("20MHz", Some(20.0)),
+        ("42", Some(42.0)),
+        ("", None),
+        ("--", None),
+        ("NULL", None),
+        ("null", None),
+        ("unknown", None),
+        ("NaNdB", None),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(parse_signal_value(input), expected, "{input:?}");
+    }
+}
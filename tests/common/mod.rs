@@ -0,0 +1,204 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use modem_exporter::Modem;
+use modem_exporter::metrics::{gather_metrics, ScrapeMetrics, StatisticsCache};
+use warp::Filter;
+use warp::hyper::Body;
+use warp::hyper::body::Bytes;
+use warp::http::{HeaderMap, Response};
+
+pub mod openmetrics;
+pub mod protobuf;
+
+pub const SESSION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><SesInfo>SessionID=mock</SesInfo><TokInfo>mocktoken</TokInfo></response>"#;
+
+pub const TRAFFIC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<CurrentConnectTime>120</CurrentConnectTime>
+<CurrentUpload>1000</CurrentUpload>
+<CurrentDownload>2000</CurrentDownload>
+<TotalUpload>30000</TotalUpload>
+<TotalDownload>40000</TotalDownload>
+<TotalConnectTime>3600</TotalConnectTime>
+</response>"#;
+
+pub const NOT_SUPPORTED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<error><code>100002</code><message></message></error>"#;
+
+pub type Requests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+pub type Headers = Arc<Mutex<Vec<(String, HeaderMap)>>>;
+pub type Bodies = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+#[derive(Clone, Default)]
+pub struct MockModem {
+    responses: HashMap<&'static str, (&'static [u8], Duration)>,
+    first: HashMap<&'static str, (&'static str, Duration)>,
+    redirects: HashMap<&'static str, &'static str>,
+    body_delays: HashMap<&'static str, Duration>,
+    cookie: Option<&'static str>,
+    rotate_tokens: bool,
+    gzip: bool,
+    requests: Requests,
+    headers: Headers,
+    bodies: Bodies,
+}
+
+impl MockModem {
+    pub fn new() -> Self {
+        Self::default()
+            .with("/api/webserver/SesTokInfo", SESSION)
+            .with("/api/monitoring/traffic-statistics", TRAFFIC)
+    }
+
+    pub fn with(self, path: &'static str, body: &'static str) -> Self {
+        self.with_delay(path, body, Duration::ZERO)
+    }
+
+    pub fn with_delay(mut self, path: &'static str, body: &'static str, delay: Duration) -> Self {
+        self.responses.insert(path, (body.as_bytes(), delay));
+        self
+    }
+
+    pub fn with_bytes(mut self, path: &'static str, body: &'static [u8]) -> Self {
+        self.responses.insert(path, (body, Duration::ZERO));
+        self
+    }
+
+    pub fn with_first(self, path: &'static str, body: &'static str) -> Self {
+        self.with_first_delay(path, body, Duration::ZERO)
+    }
+
+    pub fn with_first_delay(mut self, path: &'static str, body: &'static str, delay: Duration) -> Self {
+        self.first.insert(path, (body, delay));
+        self
+    }
+
+    pub fn with_first_redirect(mut self, path: &'static str, location: &'static str) -> Self {
+        self.redirects.insert(path, location);
+        self
+    }
+
+    pub fn with_body_delay(mut self, path: &'static str, delay: Duration) -> Self {
+        self.body_delays.insert(path, delay);
+        self
+    }
+
+    pub fn with_cookie(mut self, cookie: &'static str) -> Self {
+        self.cookie = Some(cookie);
+        self
+    }
+
+    pub fn rotate_tokens(mut self) -> Self {
+        self.rotate_tokens = true;
+        self
+    }
+
+    pub fn requests(&self) -> Requests {
+        self.requests.clone()
+    }
+
+    pub fn headers(&self) -> Headers {
+        self.headers.clone()
+    }
+
+    pub fn bodies(&self) -> Bodies {
+        self.bodies.clone()
+    }
+
+    pub fn gzip(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    pub fn spawn(self) -> String {
+        self.spawn_at(Ipv4Addr::LOCALHOST.into())
+    }
+
+    pub fn spawn_at(self, ip: IpAddr) -> String {
+        let responses = Arc::new(self.responses);
+        let first = Arc::new(Mutex::new(self.first));
+        let redirects = Arc::new(Mutex::new(self.redirects));
+        let body_delays = Arc::new(self.body_delays);
+        let cookie = self.cookie;
+        let gzip = self.gzip;
+        let rotate_tokens = self.rotate_tokens.then(|| Arc::new(AtomicUsize::new(0)));
+        let requests = self.requests;
+        let headers = self.headers;
+        let bodies = self.bodies;
+        let route = warp::path::full()
+            .and(warp::header::optional::<String>("__RequestVerificationToken"))
+            .and(warp::header::headers_cloned())
+            .and(warp::body::bytes())
+            .then(move |path: warp::path::FullPath, token: Option<String>, request_headers: HeaderMap, body: Bytes| {
+                let responses = responses.clone();
+                let body_delay = body_delays.get(path.as_str()).copied();
+                requests.lock().unwrap().push((path.as_str().to_string(), token));
+                headers.lock().unwrap().push((path.as_str().to_string(), request_headers));
+                bodies.lock().unwrap().push((path.as_str().to_string(), body.to_vec()));
+                let first = first.lock().unwrap().remove(path.as_str());
+                let redirect = redirects.lock().unwrap().remove(path.as_str());
+                let rotated = rotate_tokens.as_ref()
+                    .map(|counter| format!("token{}", counter.fetch_add(1, Ordering::Relaxed)));
+                async move {
+                    if let Some(location) = redirect {
+                        return Response::builder().status(302).header("Location", location).body(Body::empty());
+                    }
+                    let (body, delay) = match first {
+                        Some((body, delay)) => (body.as_bytes(), delay),
+                        None => responses.get(path.as_str()).copied().unwrap_or((NOT_SUPPORTED.as_bytes(), Duration::ZERO)),
+                    };
+                    tokio::time::sleep(delay).await;
+                    let mut response = Response::builder().header("Content-Type", "text/xml");
+                    if let Some(cookie) = cookie {
+                        response = response.header("Set-Cookie", cookie);
+                    }
+                    if let Some(token) = rotated {
+                        response = response.header("__RequestVerificationToken", token);
+                    }
+                    let body = if gzip {
+                        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                        encoder.write_all(body).unwrap();
+                        response = response.header("Content-Encoding", "gzip");
+                        encoder.finish().unwrap()
+                    } else {
+                        body.to_vec()
+                    };
+                    match body_delay {
+                        Some(delay) => {
+                            let (mut sender, delayed) = Body::channel();
+                            tokio::spawn(async move {
+                                tokio::time::sleep(delay).await;
+                                let _ = sender.send_data(body.into()).await;
+                            });
+                            response.body(delayed)
+                        }
+                        None => response.body(Body::from(body)),
+                    }
+                }
+            });
+
+        let (address, server): (SocketAddr, _) = warp::serve(route).bind_ephemeral((ip, 0));
+        tokio::spawn(server);
+        format!("http://{address}")
+    }
+}
+
+/// Gathers metrics from a default-configured modem backed by `mock`.
+pub async fn gather(mock: MockModem) -> String {
+    gather_modem(&Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap()).await
+}
+
+/// Gathers metrics from `modem` with fresh scrape metrics and no statistics caching.
+pub async fn gather_modem(modem: &Modem) -> String {
+    gather_metrics(modem, &ScrapeMetrics::new(), &StatisticsCache::new(None)).await.unwrap()
+}
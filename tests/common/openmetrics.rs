@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+
+pub struct Family {
+    pub metric_type: String,
+    pub unit: Option<String>,
+    pub samples: Vec<String>,
+}
+
+fn is_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_labels(mut labels: &str) -> Result<Vec<(String, String)>, String> {
+    let mut parsed = Vec::new();
+    while !labels.is_empty() {
+        let (name, rest) = labels.split_once("=\"").ok_or_else(|| format!("malformed labels: {labels:?}"))?;
+        if !is_label_name(name) {
+            return Err(format!("invalid label name: {name:?}"));
+        }
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, 'n')) => value.push('\n'),
+                    other => return Err(format!("invalid escape in label {name}: {other:?}")),
+                },
+                Some((i, '"')) => break i,
+                Some((_, '\n')) => return Err(format!("raw newline in label {name}")),
+                Some((_, c)) => value.push(c),
+                None => return Err(format!("unterminated label value: {name}")),
+            }
+        };
+        if parsed.iter().any(|(existing, _)| existing == name) {
+            return Err(format!("duplicate label: {name}"));
+        }
+        parsed.push((name.to_string(), value));
+        labels = &rest[end + 1..];
+        labels = match labels.strip_prefix(',') {
+            Some(rest) => rest,
+            None if labels.is_empty() => labels,
+            None => return Err(format!("expected ',' between labels, got {labels:?}")),
+        };
+    }
+    Ok(parsed)
+}
+
+fn parse_value(value: &str, metric_type: &str) -> Result<(), String> {
+    let parsed = match value {
+        "+Inf" => f64::INFINITY,
+        "-Inf" => f64::NEG_INFINITY,
+        "NaN" => f64::NAN,
+        _ => value.parse::<f64>().map_err(|_| format!("invalid value: {value:?}"))?,
+    };
+    if metric_type == "counter" && (parsed.is_nan() || parsed < 0.0) {
+        return Err(format!("negative or NaN counter value: {value}"));
+    }
+    Ok(())
+}
+
+fn sample_suffixes(metric_type: &str) -> &'static [&'static str] {
+    match metric_type {
+        "counter" => &["_total", "_created"],
+        "histogram" => &["_bucket", "_count", "_sum", "_created"],
+        "gaugehistogram" => &["_bucket", "_gcount", "_gsum"],
+        "summary" => &["", "_count", "_sum", "_created"],
+        "info" => &["_info"],
+        _ => &[""],
+    }
+}
+
+/// Validates an exposition against the OpenMetrics 1.0 text format and returns its families.
+pub fn parse(exposition: &str) -> Result<HashMap<String, Family>, String> {
+    let body = exposition.strip_suffix("# EOF\n").ok_or("exposition must end with \"# EOF\\n\"")?;
+    let mut families = HashMap::<String, Family>::new();
+    let mut current: Option<String> = None;
+    let mut seen_samples = HashSet::new();
+
+    for line in body.lines() {
+        if let Some(descriptor) = line.strip_prefix("# ") {
+            let mut parts = descriptor.splitn(3, ' ');
+            let kind = parts.next().unwrap_or_default();
+            let name = parts.next().ok_or_else(|| format!("malformed descriptor: {line:?}"))?;
+            let text = parts.next().unwrap_or_default();
+            if !is_metric_name(name) {
+                return Err(format!("invalid metric name: {name:?}"));
+            }
+            if current.as_deref() != Some(name) {
+                if families.contains_key(name) {
+                    return Err(format!("family {name} is not contiguous"));
+                }
+                families.insert(name.to_string(), Family { metric_type: "unknown".to_string(), unit: None, samples: Vec::new() });
+                current = Some(name.to_string());
+            }
+            let family = families.get_mut(name).unwrap();
+            if !family.samples.is_empty() {
+                return Err(format!("descriptor after samples in family {name}"));
+            }
+            match kind {
+                "HELP" => {}
+                "TYPE" => {
+                    let valid = ["counter", "gauge", "histogram", "gaugehistogram", "stateset", "info", "summary", "unknown"];
+                    if !valid.contains(&text) {
+                        return Err(format!("invalid type {text:?} for {name}"));
+                    }
+                    family.metric_type = text.to_string();
+                }
+                "UNIT" => {
+                    if !name.ends_with(&format!("_{text}")) {
+                        return Err(format!("family {name} does not end with its unit {text}"));
+                    }
+                    family.unit = Some(text.to_string());
+                }
+                _ => return Err(format!("unknown descriptor: {line:?}")),
+            }
+            continue;
+        }
+
+        let (series, value) = line.rsplit_once(' ').ok_or_else(|| format!("malformed sample: {line:?}"))?;
+        let (name, labels) = match series.split_once('{') {
+            Some((name, labels)) => {
+                let labels = labels.strip_suffix('}').ok_or_else(|| format!("unterminated labels: {line:?}"))?;
+                (name, parse_labels(labels)?)
+            }
+            None => (series, Vec::new()),
+        };
+        let family_name = current.as_deref().ok_or_else(|| format!("sample without family: {line:?}"))?;
+        let family = families.get_mut(family_name).unwrap();
+        let suffix = name.strip_prefix(family_name)
+            .filter(|suffix| sample_suffixes(&family.metric_type).contains(suffix))
+            .ok_or_else(|| format!("sample {name} does not belong to {} family {family_name}", family.metric_type))?;
+        if family.metric_type == "histogram" && suffix == "_bucket" && !labels.iter().any(|(name, _)| name == "le") {
+            return Err(format!("bucket without le label: {line:?}"));
+        }
+        parse_value(value, &family.metric_type)?;
+        let mut key = labels;
+        key.sort();
+        if !seen_samples.insert((name.to_string(), key)) {
+            return Err(format!("duplicate sample: {line:?}"));
+        }
+        family.samples.push(line.to_string());
+    }
+
+    Ok(families)
+}
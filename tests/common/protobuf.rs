@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+pub enum Field<'a> {
+    Varint(u64),
+    Double(f64),
+    Bytes(&'a [u8]),
+}
+
+pub struct Family<'a> {
+    pub metric_type: u64,
+    pub help: String,
+    pub metrics: Vec<&'a [u8]>,
+}
+
+fn varint(buf: &mut &[u8]) -> Result<u64, String> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first().ok_or("truncated varint")?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("varint too long".to_string())
+}
+
+fn bytes<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], String> {
+    let len = varint(buf)? as usize;
+    let value = buf.get(..len).ok_or("truncated length-delimited field")?;
+    *buf = &buf[len..];
+    Ok(value)
+}
+
+/// Decodes the fields of one protobuf message.
+pub fn fields(mut message: &[u8]) -> Result<Vec<(u64, Field<'_>)>, String> {
+    let mut fields = Vec::new();
+    while !message.is_empty() {
+        let key = varint(&mut message)?;
+        let field = match key & 7 {
+            0 => Field::Varint(varint(&mut message)?),
+            1 => {
+                let value = message.get(..8).ok_or("truncated double")?;
+                message = &message[8..];
+                Field::Double(f64::from_le_bytes(value.try_into().unwrap()))
+            }
+            2 => Field::Bytes(bytes(&mut message)?),
+            wire_type => return Err(format!("unexpected wire type {wire_type}")),
+        };
+        fields.push((key >> 3, field));
+    }
+    Ok(fields)
+}
+
+/// Decodes length-delimited `io.prometheus.client.MetricFamily` messages keyed by family name.
+pub fn parse(mut body: &[u8]) -> Result<HashMap<String, Family<'_>>, String> {
+    let mut families = HashMap::new();
+    while !body.is_empty() {
+        let (mut name, mut family) = (None, Family { metric_type: u64::MAX, help: String::new(), metrics: Vec::new() });
+        for (number, field) in fields(bytes(&mut body)?)? {
+            match (number, field) {
+                (1, Field::Bytes(value)) => name = Some(String::from_utf8(value.to_vec()).map_err(|err| err.to_string())?),
+                (2, Field::Bytes(value)) => family.help = String::from_utf8(value.to_vec()).map_err(|err| err.to_string())?,
+                (3, Field::Varint(value)) => family.metric_type = value,
+                (4, Field::Bytes(value)) => family.metrics.push(value),
+                (5, Field::Bytes(_)) => {}
+                (number, _) => return Err(format!("unexpected MetricFamily field {number}")),
+            }
+        }
+        let name = name.ok_or("family without name")?;
+        if families.insert(name.clone(), family).is_some() {
+            return Err(format!("duplicate family {name}"));
+        }
+    }
+    Ok(families)
+}
+
+/// Returns the labels of a Metric and the value of its gauge, counter or untyped field.
+pub fn sample(metric: &[u8]) -> (Vec<(String, String)>, f64) {
+    let mut labels = Vec::new();
+    let mut value = f64::NAN;
+    for (number, field) in fields(metric).unwrap() {
+        match (number, field) {
+            (1, Field::Bytes(pair)) => {
+                let pair = fields(pair).unwrap();
+                let text = |index: usize| match pair[index].1 {
+                    Field::Bytes(text) => String::from_utf8(text.to_vec()).unwrap(),
+                    _ => panic!("label field is not a string"),
+                };
+                labels.push((text(0), text(1)));
+            }
+            (2 | 3 | 5, Field::Bytes(inner)) => {
+                if let Some((_, Field::Double(inner))) = fields(inner).unwrap().into_iter().find(|(number, _)| *number == 1) {
+                    value = inner;
+                }
+            }
+            _ => {}
+        }
+    }
+    (labels, value)
+}
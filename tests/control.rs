@@ -0,0 +1,220 @@
+mod common;
+
+use std::time::Duration;
+
+use common::MockModem;
+use modem_exporter::{Credentials, Modem, ModemError};
+use modem_exporter::metrics::encode_modem_collector;
+use modem_exporter::modem::{AvailableNetworks, NetMode, Sms};
+
+fn net_mode() -> NetMode {
+    NetMode {
+        network_mode: "03".to_string(),
+        network_band: "3FFFFFFF".to_string(),
+        lte_band: "7FFFFFFFFFFFFFFF".to_string(),
+    }
+}
+
+async fn set_net_mode(response: &'static str) -> Result<(), ModemError> {
+    let address = MockModem::new().with("/api/net/net-mode", response).spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+    modem.set_net_mode(&net_mode()).await
+}
+
+#[tokio::test]
+async fn control_requests_use_rotated_token() {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+
+    let mock = MockModem::new().with("/api/net/net-mode", OK).rotate_tokens();
+    let requests = mock.requests();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap();
+    let mode = net_mode();
+
+    modem.set_net_mode(&mode).await.unwrap();
+    modem.set_net_mode(&mode).await.unwrap();
+
+    let tokens = requests.lock().unwrap().iter()
+        .filter(|(path, _)| path == "/api/net/net-mode")
+        .map(|(_, token)| token.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(tokens.len(), 2, "{tokens:?}");
+    assert_eq!(tokens[0].as_deref(), Some("mocktoken"));
+    assert_ne!(tokens[0], tokens[1]);
+}
+
+#[tokio::test]
+async fn control_accepts_ok_response() {
+    set_net_mode(r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#).await.unwrap();
+}
+
+#[tokio::test]
+async fn control_rejects_unexpected_response() {
+    let result = set_net_mode(r#"<?xml version="1.0" encoding="UTF-8"?><response>FAIL</response>"#).await;
+    assert!(matches!(result, Err(ModemError::Decode(_))), "{result:?}");
+}
+
+#[tokio::test]
+async fn control_reports_api_error() {
+    let result = set_net_mode(r#"<?xml version="1.0" encoding="UTF-8"?>
+<error><code>100005</code><message></message></error>"#).await;
+    assert!(matches!(result, Err(ModemError::ApiError { code: 100005, .. })), "{result:?}");
+}
+
+#[tokio::test]
+async fn scan_networks_returns_available_operators() {
+    const PLMN_LIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><Networks>
+<Network><Index>0</Index><State>2</State><FullName>Operator One</FullName><ShortName>One</ShortName><Numeric>25001</Numeric><Rat>7</Rat></Network>
+<Network><Index>1</Index><State>3</State><FullName>Operator Two</FullName><ShortName>Two</ShortName><Numeric>25002</Numeric><Rat>2</Rat></Network>
+</Networks></response>"#;
+
+    let address = MockModem::new().with("/api/net/plmn-list", PLMN_LIST).spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+
+    let networks = modem.scan_networks().await.unwrap();
+
+    assert_eq!(networks.len(), 2, "{networks:?}");
+    assert_eq!(networks[0].full_name, "Operator One");
+    assert_eq!(networks[1].numeric, "25002");
+    assert_eq!(networks[1].state.as_deref(), Some("3"));
+
+    let output = encode_modem_collector(&modem, Box::new(AvailableNetworks { network: networks })).unwrap();
+    assert!(output.contains(
+        "modem_available_network_info{name=\"Operator One\",numeric=\"25001\",state=\"registered\",rat=\"LTE\"} 1\n"
+    ), "{output}");
+    assert!(output.contains(
+        "modem_available_network_info{name=\"Operator Two\",numeric=\"25002\",state=\"forbidden\",rat=\"WCDMA\"} 1\n"
+    ), "{output}");
+}
+
+#[tokio::test]
+async fn send_sms_posts_message() {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+
+    let mock = MockModem::new().with("/api/sms/send-sms", OK);
+    let requests = mock.requests();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap();
+
+    modem.send_sms(&Sms { number: "+79001234567".to_string(), text: "test".to_string() }).await.unwrap();
+
+    assert!(requests.lock().unwrap().iter().any(|(path, _)| path == "/api/sms/send-sms"));
+}
+
+#[tokio::test]
+async fn send_sms_rejects_invalid_input() {
+    let mock = MockModem::new();
+    let requests = mock.requests();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap();
+
+    for (number, text) in [("", "test"), ("12ab", "test"), ("+7900", ""), ("+7900", &"x".repeat(161))] {
+        let result = modem.send_sms(&Sms { number: number.to_string(), text: text.to_string() }).await;
+        assert!(matches!(result, Err(ModemError::Other(_))), "{number:?} {text:?}: {result:?}");
+    }
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn check_then_login_with_credentials() {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+
+    let mock = MockModem::new().with("/api/user/login", OK);
+    let requests = mock.requests();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap()
+        .with_credentials(Some(Credentials { username: "admin".to_string(), password: "secret".to_string() }));
+
+    modem.check().await.unwrap();
+    modem.login().await.unwrap();
+
+    let logins = requests.lock().unwrap().iter().filter(|(path, _)| path == "/api/user/login").count();
+    assert_eq!(logins, 2);
+}
+
+#[tokio::test]
+async fn login_on_fresh_modem_acquires_session() {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+
+    let address = MockModem::new().with("/api/user/login", OK).spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap()
+        .with_credentials(Some(Credentials { username: "admin".to_string(), password: "secret".to_string() }));
+
+    modem.login().await.unwrap();
+}
+
+#[tokio::test]
+async fn html_response_is_reported_as_session_expired() {
+    const LOGIN_PAGE: &str = "<!DOCTYPE html><html><head><title>Login</title></head><body></body></html>";
+
+    let address = MockModem::new().with("/api/net/net-mode", LOGIN_PAGE).spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+
+    let result = modem.get_net_mode().await;
+
+    assert!(matches!(result, Err(ModemError::SessionExpired)), "{result:?}");
+}
+
+#[tokio::test]
+async fn logout_clears_session() {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+    const NET_MODE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><NetworkMode>00</NetworkMode><NetworkBand>3FFFFFFF</NetworkBand><LTEBand>7FFFFFFFFFFFFFFF</LTEBand></response>"#;
+
+    let mock = MockModem::new()
+        .with("/api/user/login", OK)
+        .with("/api/user/logout", OK)
+        .with("/api/net/net-mode", NET_MODE);
+    let requests = mock.requests();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap()
+        .with_credentials(Some(Credentials { username: "admin".to_string(), password: "secret".to_string() }));
+    let count = |path: &str| requests.lock().unwrap().iter().filter(|(request, _)| request == path).count();
+
+    modem.get_net_mode().await.unwrap();
+    modem.get_net_mode().await.unwrap();
+    assert_eq!(count("/api/webserver/SesTokInfo"), 1);
+
+    modem.logout().await.unwrap();
+    assert_eq!(count("/api/user/logout"), 1);
+
+    modem.get_net_mode().await.unwrap();
+    assert_eq!(count("/api/webserver/SesTokInfo"), 2);
+    assert_eq!(count("/api/user/login"), 2);
+}
+
+#[tokio::test]
+async fn control_requests_serialize_exact_body() {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+
+    let mock = MockModem::new().with("/api/device/control", OK).with("/api/net/net-mode", OK);
+    let bodies = mock.bodies();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap();
+
+    modem.reboot().await.unwrap();
+    modem.set_net_mode(&net_mode()).await.unwrap();
+
+    let bodies = bodies.lock().unwrap();
+    let body = |path: &str| bodies.iter().find(|(request, _)| request == path).map(|(_, body)| body.clone()).unwrap();
+    assert_eq!(
+        body("/api/device/control"),
+        br#"<?xml version="1.0" encoding="UTF-8"?><request><Control>1</Control></request>"#,
+    );
+    assert_eq!(
+        body("/api/net/net-mode"),
+        br#"<?xml version="1.0" encoding="UTF-8"?><request><NetworkMode>03</NetworkMode><NetworkBand>3FFFFFFF</NetworkBand><LTEBand>7FFFFFFFFFFFFFFF</LTEBand></request>"#,
+    );
+}
+
+#[tokio::test]
+async fn get_raw_redacts_identity_when_enabled() {
+    const DEVICE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><DeviceName>E3372</DeviceName><SerialNumber>G4PDW1234</SerialNumber><Imei>861234567890123</Imei><Imsi>001010123456789</Imsi><Iccid>8901</Iccid><Msisdn/><MacAddress1>00:11:22:33:44:55</MacAddress1><WifiMacAddrWl0>66:77:88:99:AA:BB</WifiMacAddrWl0><SoftwareVersion>22.200</SoftwareVersion></response>"#;
+
+    let address = MockModem::new().with("/api/device/information", DEVICE).spawn();
+    let plain = Modem::new(address.clone(), Duration::from_secs(5), false).unwrap();
+    let redacted = Modem::new(address, Duration::from_secs(5), false).unwrap().with_redact_identity(true);
+
+    assert_eq!(plain.get_raw("/api/device/information").await.unwrap(), DEVICE);
+    assert_eq!(
+        redacted.get_raw("/api/device/information").await.unwrap(),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><DeviceName>E3372</DeviceName><SerialNumber></SerialNumber><Imei></Imei><Imsi></Imsi><Iccid></Iccid><Msisdn/><MacAddress1></MacAddress1><WifiMacAddrWl0></WifiMacAddrWl0><SoftwareVersion>22.200</SoftwareVersion></response>"#,
+    );
+}
@@ -0,0 +1,848 @@
+mod common;
+
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv6Addr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use common::MockModem;
+use modem_exporter::Modem;
+use modem_exporter::metrics::{check_collectors, gather_json, gather_metrics, ScrapeMetrics, StatisticsCache};
+use modem_exporter::modem::{normalize_base_url, LifetimeTraffic, DEFAULT_ACCEPT_LANGUAGE, DEFAULT_USER_AGENT};
+
+#[tokio::test]
+async fn gather_metrics_renders_traffic_statistics() {
+    let output = common::gather(MockModem::new()).await;
+
+    assert!(output.contains("modem_up 1\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 1000.0\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"download\"} 2000.0\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"download\"} 40000\n"), "{output}");
+    assert!(output.contains("modem_session_connect_duration_seconds 120\n"), "{output}");
+    assert!(output.contains("modem_connect_duration_seconds_total 3600\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"signal\"} 0\n"), "{output}");
+    assert!(output.ends_with("# EOF\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_build_info_when_modem_is_unreachable() {
+    let modem = Modem::new("http://127.0.0.1:1", Duration::from_secs(5), false).unwrap();
+
+    let output = common::gather_modem(&modem).await;
+
+    let version = format!("version=\"{}\"", env!("CARGO_PKG_VERSION"));
+    let build_info = output.lines().find(|line| line.starts_with("modem_exporter_build_info{")).expect(&output);
+    assert!(build_info.contains(&version), "{build_info}");
+    assert!(build_info.ends_with(" 1"), "{build_info}");
+    assert!(output.contains("modem_up 0\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_fetches_endpoints_concurrently() {
+    const SIGNAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rssi>-51dBm</rssi></response>"#;
+    const DELAY: Duration = Duration::from_millis(300);
+
+    let address = MockModem::new()
+        .with_delay("/api/monitoring/traffic-statistics", common::TRAFFIC, DELAY)
+        .with_delay("/api/device/signal", SIGNAL, DELAY)
+        .with_delay("/api/monitoring/status", common::NOT_SUPPORTED, DELAY)
+        .with_delay("/api/device/information", common::NOT_SUPPORTED, DELAY)
+        .spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+
+    let start = Instant::now();
+    let output = common::gather_modem(&modem).await;
+    let elapsed = start.elapsed();
+
+    assert!(output.contains("modem_signal_rssi_dbm -51.0\n"), "{output}");
+    assert!(elapsed >= DELAY, "{elapsed:?}");
+    assert!(elapsed < DELAY * 2, "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn gather_metrics_supports_ipv6_modem_address() {
+    let address = MockModem::new().spawn_at(Ipv6Addr::LOCALHOST.into());
+    let address = address.trim_start_matches("http://");
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+
+    let output = common::gather_modem(&modem).await;
+
+    assert!(output.contains("modem_up 1\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_supports_ipv6_modem_address_with_zone() {
+    assert_eq!(normalize_base_url("fe80::1%eth0"), "http://[fe80::1%25eth0]");
+    assert_eq!(normalize_base_url("http://[fe80::1%eth0]:8080/"), "http://[fe80::1%25eth0]:8080");
+    assert_eq!(normalize_base_url("http://[fe80::1%25eth0]"), "http://[fe80::1%25eth0]");
+    assert!(Modem::new("fe80::1%no-such-interface", Duration::from_secs(5), false).is_err());
+
+    let address = MockModem::new().spawn_at(Ipv6Addr::LOCALHOST.into());
+    let port = address.rsplit(':').next().unwrap();
+    for address in [format!("http://[::1%25lo]:{port}"), format!("[::1%1]:{port}")] {
+        let modem = Modem::new(address.as_str(), Duration::from_secs(5), false).unwrap();
+
+        let output = common::gather_modem(&modem).await;
+
+        assert!(output.contains("modem_up 1\n"), "{address} {output}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_accepts_camel_case_traffic_statistics() {
+    const TRAFFIC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<currentConnectTime> 120 </currentConnectTime>
+<currentUpload>1000</currentUpload>
+<currentDownload>2000</currentDownload>
+<totalUpload>30000</totalUpload>
+<totalDownload>40000</totalDownload>
+<totalConnectTime>3600</totalConnectTime>
+</response>"#;
+
+    let output = common::gather(MockModem::new().with("/api/monitoring/traffic-statistics", TRAFFIC)).await;
+
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 1000.0\n"), "{output}");
+    assert!(output.contains("modem_session_connect_duration_seconds 120\n"), "{output}");
+    assert!(output.contains("modem_connect_duration_seconds_total 3600\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_decodes_gzip_responses() {
+    let output = common::gather(MockModem::new().gzip()).await;
+
+    assert!(output.contains("modem_up 1\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 1000.0\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_describes_every_metric_family() {
+    const SIGNAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><rssi>-51dBm</rssi><rsrp>-80dBm</rsrp><sinr>12dB</sinr><band>3</band><dlbandwidth>20MHz</dlbandwidth></response>"#;
+    const STATUS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><ConnectionStatus>901</ConnectionStatus><CurrentNetworkType>19</CurrentNetworkType><RoamingStatus>0</RoamingStatus><SignalIcon>4</SignalIcon></response>"#;
+    const DEVICE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><DeviceName>E3372</DeviceName><SerialNumber>SN</SerialNumber><Imei>1</Imei><Imsi>2</Imsi><HardwareVersion>HW</HardwareVersion><SoftwareVersion>SW</SoftwareVersion><Uptime>600</Uptime><Iccid>89</Iccid></response>"#;
+    const UNITS: [&str; 7] = ["seconds", "bytes", "bytes_per_second", "dbm", "db", "mhz", "celsius"];
+
+    let mock = MockModem::new()
+        .with("/api/device/signal", SIGNAL)
+        .with("/api/monitoring/status", STATUS)
+        .with("/api/device/information", DEVICE);
+
+    let output = common::gather(mock).await;
+
+    let mut help = HashSet::new();
+    let mut types = HashMap::new();
+    let mut units = HashMap::new();
+    let mut samples = Vec::new();
+    for line in output.lines() {
+        let mut parts = line.splitn(4, ' ');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("#"), Some("HELP"), Some(name), Some(_)) => { help.insert(name); }
+            (Some("#"), Some("TYPE"), Some(name), Some(kind)) => { types.insert(name, kind); }
+            (Some("#"), Some("UNIT"), Some(name), Some(unit)) => { units.insert(name, unit); }
+            (Some("#"), Some("EOF"), None, None) => {}
+            (Some("#"), ..) => panic!("unexpected line: {line}"),
+            _ => samples.push(line.split(['{', ' ']).next().unwrap()),
+        }
+    }
+
+    assert!(types.contains_key("modem_signal_rssi_dbm"), "{output}");
+    assert!(types.contains_key("modem_uptime_seconds"), "{output}");
+    for name in types.keys() {
+        assert!(help.contains(name), "missing HELP for {name}");
+        let unit = UNITS.iter().find(|unit| name.ends_with(&format!("_{unit}")));
+        assert_eq!(units.get(name), unit, "UNIT mismatch for {name}");
+    }
+    for sample in samples {
+        let (family, kind) = types.iter()
+            .filter(|(name, _)| sample.starts_with(*name))
+            .max_by_key(|(name, _)| name.len())
+            .unwrap_or_else(|| panic!("missing TYPE for {sample}"));
+        let suffix = &sample[family.len()..];
+        match *kind {
+            "counter" => assert_eq!(suffix, "_total", "{sample}"),
+            "histogram" => assert!(["_bucket", "_sum", "_count"].contains(&suffix), "{sample}"),
+            _ => assert_eq!(suffix, "", "{sample}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_refreshes_stale_session() {
+    const WRONG_TOKEN: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<error><code>125002</code><message></message></error>"#;
+
+    let output = common::gather(MockModem::new().with_first("/api/monitoring/traffic-statistics", WRONG_TOKEN)).await;
+
+    assert!(output.contains("modem_up 1\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 1000.0\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_applies_metric_prefix() {
+    let address = MockModem::new().spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap().with_metric_prefix("hilink_");
+
+    let output = common::gather_modem(&modem).await;
+
+    assert!(output.contains("hilink_up 1\n"), "{output}");
+    assert!(output.contains("hilink_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{output}");
+    for line in output.lines().filter(|line| *line != "# EOF") {
+        let name = line.strip_prefix("# ").map_or(line, |line| line.split(' ').nth(1).unwrap());
+        assert!(name.starts_with("hilink_"), "{line}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_serves_concurrent_scrapes() {
+    const DELAY: Duration = Duration::from_millis(300);
+
+    let address = MockModem::new()
+        .with_delay("/api/monitoring/traffic-statistics", common::TRAFFIC, DELAY)
+        .spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+    let scrape = ScrapeMetrics::new();
+    let cache = StatisticsCache::new(None);
+
+    let start = Instant::now();
+    let outputs = tokio::join!(
+        gather_metrics(&modem, &scrape, &cache),
+        gather_metrics(&modem, &scrape, &cache),
+        gather_metrics(&modem, &scrape, &cache),
+        gather_metrics(&modem, &scrape, &cache),
+    );
+    let elapsed = start.elapsed();
+
+    for output in [outputs.0, outputs.1, outputs.2, outputs.3] {
+        let output = output.unwrap();
+        assert!(output.contains("modem_up 1\n"), "{output}");
+    }
+    assert!(elapsed < DELAY * 2, "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn gather_metrics_computes_traffic_rates_between_scrapes() {
+    let address = MockModem::new().spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+    let scrape = ScrapeMetrics::new();
+    let cache = StatisticsCache::new(None);
+
+    let first = gather_metrics(&modem, &scrape, &cache).await.unwrap();
+    let second = gather_metrics(&modem, &scrape, &cache).await.unwrap();
+
+    assert!(!first.contains("modem_current_upload_rate_bytes_per_second"), "{first}");
+    assert!(second.contains("modem_current_upload_rate_bytes_per_second 0\n"), "{second}");
+    assert!(second.contains("modem_current_download_rate_bytes_per_second 0\n"), "{second}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reads_session_from_landing_page() {
+    const LANDING_PAGE: &str = r#"<!DOCTYPE html>
+<html><head>
+<meta http-equiv="X-UA-Compatible" content="IE=edge">
+<meta name="csrf_token" content="landingtoken">
+</head><body></body></html>"#;
+
+    let mock = MockModem::default()
+        .with("/", LANDING_PAGE)
+        .with("/api/monitoring/traffic-statistics", common::TRAFFIC)
+        .with_cookie("SessionID=landing; path=/; HttpOnly");
+
+    let output = common::gather(mock).await;
+
+    assert!(output.contains("modem_up 1\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_returns_partial_results_at_deadline() {
+    const SIGNAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rssi>-51dBm</rssi></response>"#;
+
+    let address = MockModem::new().with_delay("/api/device/signal", SIGNAL, Duration::from_secs(2)).spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap()
+        .with_scrape_deadline(Some(Duration::from_millis(300)));
+
+    let start = Instant::now();
+    let output = common::gather_modem(&modem).await;
+    let elapsed = start.elapsed();
+
+    assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+    assert!(output.contains("modem_scrape_timeout 1\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"signal\"} 0\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_applies_instance_labels() {
+    let address = MockModem::new().spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap()
+        .with_labels(vec![("modem".to_string(), "home \"lte\"".to_string())]);
+
+    let output = common::gather_modem(&modem).await;
+
+    assert!(output.contains("modem_up{modem=\"home \\\"lte\\\"\"} 1\n"), "{output}");
+    for line in output.lines().filter(|line| !line.starts_with('#')) {
+        assert!(line.contains("modem=\"home \\\"lte\\\"\""), "{line}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_refreshes_session_instead_of_following_redirects() {
+    const HOME: &str = "<!DOCTYPE html><html><body></body></html>";
+
+    let mock = MockModem::new()
+        .with("/html/home.html", HOME)
+        .with_first_redirect("/api/monitoring/traffic-statistics", "/html/home.html");
+    let requests = mock.requests();
+
+    let output = common::gather(mock).await;
+
+    assert!(output.contains("modem_up 1\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 1000.0\n"), "{output}");
+    let requests = requests.lock().unwrap();
+    assert!(requests.iter().all(|(path, _)| path != "/html/home.html"), "{requests:?}");
+    assert_eq!(requests.iter().filter(|(path, _)| path == "/api/webserver/SesTokInfo").count(), 2, "{requests:?}");
+}
+
+#[tokio::test]
+async fn gather_metrics_sends_browser_headers() {
+    let mock = MockModem::new();
+    let headers = mock.headers();
+    let address = mock.spawn();
+    let modem = Modem::new(address.as_str(), Duration::from_secs(5), false).unwrap()
+        .with_user_agent(Some("test-agent".to_string()));
+
+    common::gather_modem(&modem).await;
+
+    let headers = headers.lock().unwrap();
+    let (_, traffic) = headers.iter().find(|(path, _)| path == "/api/monitoring/traffic-statistics").unwrap();
+    assert_eq!(traffic.get("user-agent").unwrap(), "test-agent");
+    assert_eq!(traffic.get("referer").unwrap().to_str().unwrap(), format!("{address}/html/home.html"));
+}
+
+#[tokio::test]
+async fn gather_metrics_sends_default_user_agent() {
+    let mock = MockModem::new();
+    let headers = mock.headers();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap();
+
+    common::gather_modem(&modem).await;
+
+    let headers = headers.lock().unwrap();
+    assert!(!headers.is_empty());
+    for (path, headers) in headers.iter() {
+        assert_eq!(headers.get("user-agent").unwrap(), DEFAULT_USER_AGENT, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_sends_accept_language() {
+    let mock = MockModem::new();
+    let headers = mock.headers();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap();
+
+    common::gather_modem(&modem).await;
+
+    let headers = headers.lock().unwrap();
+    assert!(!headers.is_empty());
+    for (path, headers) in headers.iter() {
+        assert_eq!(headers.get("accept-language").unwrap(), DEFAULT_ACCEPT_LANGUAGE, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_sends_configured_accept_language() {
+    let mock = MockModem::new();
+    let headers = mock.headers();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap()
+        .with_accept_language(Some("de-DE".to_string()));
+
+    common::gather_modem(&modem).await;
+
+    let headers = headers.lock().unwrap();
+    let (_, plmn) = headers.iter().find(|(path, _)| path == "/api/net/current-plmn").unwrap();
+    assert_eq!(plmn.get("accept-language").unwrap(), "de-DE");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_failed_connection_status() {
+    const STATUS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><ConnectionStatus>113</ConnectionStatus><CurrentNetworkType>0</CurrentNetworkType><RoamingStatus>0</RoamingStatus></response>"#;
+
+    let output = common::gather(MockModem::new().with("/api/monitoring/status", STATUS)).await;
+
+    assert!(output.contains("modem_connection_status 0\n"), "{output}");
+    assert!(output.contains("modem_connection_status_code 113\n"), "{output}");
+    assert!(output.contains("modem_connection_failed 1\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_transcodes_declared_encoding() {
+    const PLMN: &[u8] = b"<?xml version=\"1.0\" encoding=\"GBK\"?>\n\
+<response><State>0</State><FullName>\xd6\xd0\xb9\xfa\xd2\xc6\xb6\xaf</FullName><ShortName>CMCC</ShortName>\
+<Numeric>46000</Numeric><Rat>7</Rat></response>";
+
+    let output = common::gather(MockModem::new().with_bytes("/api/net/current-plmn", PLMN)).await;
+
+    assert!(output.contains("modem_collector_up{collector=\"plmn\"} 1\n"), "{output}");
+    assert!(output.contains("name=\"中国移动\",short_name=\"CMCC\""), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_sampled_signal_since_last_scrape() {
+    const WEAK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rsrp>-100dBm</rsrp></response>"#;
+    const STRONG: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rsrp>-80dBm</rsrp></response>"#;
+
+    let address = MockModem::new()
+        .with_first("/api/device/signal", WEAK)
+        .with("/api/device/signal", STRONG)
+        .spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+    let scrape = ScrapeMetrics::new();
+    let cache = StatisticsCache::new(None);
+
+    modem.sample_signal().await.unwrap();
+    modem.sample_signal().await.unwrap();
+    let first = gather_metrics(&modem, &scrape, &cache).await.unwrap();
+    let second = gather_metrics(&modem, &scrape, &cache).await.unwrap();
+
+    assert!(first.contains("modem_signal_rsrp_min_dbm -100.0\n"), "{first}");
+    assert!(first.contains("modem_signal_rsrp_max_dbm -80.0\n"), "{first}");
+    assert!(first.contains("modem_signal_rsrp_avg_dbm -90.0\n"), "{first}");
+    assert!(!second.contains("modem_signal_rsrp_min_dbm"), "{second}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_cell_identity() {
+    const DECIMAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><cell_id>29471756</cell_id><pci>123</pci><tac>4321</tac></response>"#;
+    const HEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><cell_id>0x1C1B40C</cell_id><lac>10E1</lac></response>"#;
+
+    for (signal, labels) in [(DECIMAL, ",tac=\"4321\",pci=\"123\""), (HEX, ",tac=\"10E1\",pci=\"\"")] {
+        let output = common::gather(MockModem::new().with("/api/device/signal", signal)).await;
+
+        let expected = format!("modem_cell_info{{cell_id=\"29471756\",enb_id=\"115124\",sector_id=\"12\"{labels}}} 1\n");
+        assert!(output.contains(&expected), "{output}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_thermal_protection_when_present() {
+    const STATUS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><ConnectionStatus>901</ConnectionStatus><CurrentNetworkType>19</CurrentNetworkType><RoamingStatus>0</RoamingStatus>"#;
+
+    for (extra, expected) in [("<OverheatStatus>1</OverheatStatus>", Some(1)), ("", None)] {
+        let status = format!("{STATUS}{extra}</response>").leak();
+        let output = common::gather(MockModem::new().with("/api/monitoring/status", status)).await;
+
+        assert!(output.contains("modem_connection_status 1\n"), "{output}");
+        match expected {
+            Some(value) => assert!(output.contains(&format!("modem_thermal_throttled {value}\n")), "{output}"),
+            None => assert!(!output.contains("modem_thermal_throttled"), "{output}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_session_start_timestamp() {
+    let output = common::gather(MockModem::new()).await;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as f64;
+    let start = output.lines()
+        .find_map(|line| line.strip_prefix("modem_session_start_timestamp_seconds "))
+        .expect(&output)
+        .parse::<f64>()
+        .unwrap();
+    assert!((now - 125.0..=now - 115.0).contains(&start), "{start} {now}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_connection_established_timestamp() {
+    let output = common::gather(MockModem::new()).await;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let established = output.lines()
+        .find_map(|line| line.strip_prefix("modem_connection_established_timestamp_seconds "))
+        .expect(&output)
+        .parse::<i64>()
+        .unwrap();
+    assert!((now - 125..=now - 115).contains(&established), "{established} {now}");
+}
+
+#[tokio::test]
+async fn gather_metrics_omits_session_start_without_connection() {
+    const DISCONNECTED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><CurrentConnectTime>0</CurrentConnectTime><CurrentUpload>0</CurrentUpload><CurrentDownload>0</CurrentDownload>
+<TotalUpload>0</TotalUpload><TotalDownload>0</TotalDownload><TotalConnectTime>0</TotalConnectTime></response>"#;
+
+    let unreachable = Modem::new("http://127.0.0.1:1", Duration::from_secs(5), false).unwrap();
+    let output = common::gather_modem(&unreachable).await;
+    assert!(!output.contains("session_start_timestamp"), "{output}");
+
+    let output = common::gather(MockModem::new().with("/api/monitoring/traffic-statistics", DISCONNECTED)).await;
+    assert!(!output.contains("session_start_timestamp"), "{output}");
+    assert!(!output.contains("connection_established_timestamp"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_firmware_update() {
+    const UPDATE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><NewVersion>22.200.15.00.00</NewVersion></response>"#;
+
+    let output = common::gather(MockModem::new().with("/api/online-update/check-new-version", UPDATE)).await;
+
+    assert!(output.contains("modem_firmware_update_available 1\n"), "{output}");
+    assert!(output.contains("modem_firmware_update_info{version=\"22.200.15.00.00\"} 1\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_classifies_connection_quality() {
+    const EXCELLENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rsrp>-75dBm</rsrp><sinr>25dB</sinr></response>"#;
+    const FAIR: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rsrp>-85dBm</rsrp><sinr>5dB</sinr></response>"#;
+    const POOR: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rsrp>-110dBm</rsrp></response>"#;
+    const MISSING: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rssi>-51dBm</rssi></response>"#;
+
+    for (signal, expected) in [(EXCELLENT, Some((3, "excellent"))), (FAIR, Some((1, "fair"))), (POOR, Some((0, "poor"))), (MISSING, None)] {
+        let output = common::gather(MockModem::new().with("/api/device/signal", signal)).await;
+
+        match expected {
+            Some((quality, name)) => {
+                assert!(output.contains(&format!("modem_connection_quality {quality}\n")), "{output}");
+                assert!(output.contains(&format!("modem_connection_quality_info{{quality=\"{name}\"}} 1\n")), "{output}");
+            }
+            None => assert!(!output.contains("modem_connection_quality"), "{output}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_tolerates_unusual_traffic_values() {
+    const TRAFFIC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<CurrentConnectTime>1 234</CurrentConnectTime>
+<CurrentUpload>18446744073709551615</CurrentUpload>
+<CurrentDownload>1.5e3</CurrentDownload>
+<TotalUpload>18446744073709551616</TotalUpload>
+<TotalDownload>9007199254740993</TotalDownload>
+<TotalConnectTime>-5</TotalConnectTime>
+</response>"#;
+
+    let output = common::gather(MockModem::new().with("/api/monitoring/traffic-statistics", TRAFFIC)).await;
+
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+    assert!(output.contains("modem_session_connect_duration_seconds 1234\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 18446744073709553000.0\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"download\"} 1500.0\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"upload\"} 18446744073709551615\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"download\"} 9007199254740993\n"), "{output}");
+    assert!(output.contains("modem_connect_duration_seconds_total 0\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_json_serializes_statistics_with_snake_case_fields() {
+    let address = MockModem::new().spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap();
+
+    let output = gather_json(&modem, &ScrapeMetrics::new(), &StatisticsCache::new(None)).await.unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(json["up"], true, "{output}");
+    assert_eq!(json["stale"], false, "{output}");
+    assert_eq!(json["statistics"]["traffic"]["current_upload"], 1000, "{output}");
+    assert_eq!(json["statistics"]["traffic"]["total_download"], 40000, "{output}");
+    assert_eq!(json["statistics"]["signal"], serde_json::Value::Null, "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_probes_alternate_traffic_paths() {
+    let mock = MockModem::new()
+        .with("/api/monitoring/traffic-statistics", common::NOT_SUPPORTED)
+        .with("/api/monitoring/statistic", common::TRAFFIC);
+
+    let output = common::gather(mock).await;
+
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_uses_configured_traffic_path() {
+    let address = MockModem::new()
+        .with("/api/monitoring/traffic-statistics", common::NOT_SUPPORTED)
+        .with("/api/vendor/traffic", common::TRAFFIC)
+        .spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap()
+        .with_traffic_path(Some("/api/vendor/traffic".to_string()));
+
+    let output = common::gather_modem(&modem).await;
+
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{output}");
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_lte_lock() {
+    const LTE_ONLY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><NetworkMode>03</NetworkMode><NetworkBand>3FFFFFFF</NetworkBand><LTEBand>7FFFFFFFFFFFFFFF</LTEBand></response>"#;
+    const AUTO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><NetworkMode>00</NetworkMode><NetworkBand>3FFFFFFF</NetworkBand><LTEBand>7FFFFFFFFFFFFFFF</LTEBand></response>"#;
+
+    for (net_mode, locked) in [(LTE_ONLY, 1), (AUTO, 0)] {
+        let output = common::gather(MockModem::new().with("/api/net/net-mode", net_mode)).await;
+
+        assert!(output.contains(&format!("modem_network_mode_locked_lte {locked}\n")), "{output}");
+    }
+}
+
+#[tokio::test]
+async fn check_collectors_reports_each_enabled_collector() {
+    let address = MockModem::new().spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap()
+        .with_collectors(Some(vec!["traffic".to_string(), "signal".to_string()]));
+
+    let collectors = check_collectors(&modem).await.unwrap();
+
+    assert_eq!(collectors, [("traffic", true), ("signal", false)]);
+}
+
+#[tokio::test]
+async fn scrape_metrics_remember_first_successful_gather() {
+    let scrape = ScrapeMetrics::new();
+    let unreachable = Modem::new("http://127.0.0.1:1", Duration::from_secs(5), false).unwrap();
+    let modem = Modem::new(MockModem::new().spawn(), Duration::from_secs(5), false).unwrap();
+
+    gather_metrics(&unreachable, &scrape, &StatisticsCache::new(None)).await.unwrap();
+    assert!(!scrape.ever_succeeded());
+
+    gather_metrics(&modem, &scrape, &StatisticsCache::new(None)).await.unwrap();
+    assert!(scrape.ever_succeeded());
+
+    gather_metrics(&unreachable, &scrape, &StatisticsCache::new(None)).await.unwrap();
+    assert!(!scrape.succeeded());
+    assert!(scrape.ever_succeeded());
+}
+
+#[tokio::test]
+async fn gather_metrics_reuses_session_across_scrapes() {
+    let mock = MockModem::new();
+    let requests = mock.requests();
+    let modem = Modem::new(mock.spawn(), Duration::from_secs(5), false).unwrap();
+
+    common::gather_modem(&modem).await;
+    common::gather_modem(&modem).await;
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.iter().filter(|(path, _)| path == "/api/webserver/SesTokInfo").count(), 1, "{requests:?}");
+    assert_eq!(requests.iter().filter(|(path, _)| path == "/api/monitoring/traffic-statistics").count(), 2, "{requests:?}");
+}
+
+#[tokio::test]
+async fn gather_metrics_output_parses_as_strict_openmetrics() {
+    let output = common::gather(MockModem::new()).await;
+
+    assert!(output.ends_with("# EOF\n"), "{output}");
+    let families = common::openmetrics::parse(&output).unwrap_or_else(|err| panic!("{err}\n{output}"));
+    assert!(families.values().any(|family| !family.samples.is_empty()));
+    assert!(families["modem_transferred_bytes"].samples.iter().all(|sample| sample.starts_with("modem_transferred_bytes_total{")));
+}
+
+#[tokio::test]
+async fn gather_metrics_declares_counter_and_gauge_types() {
+    const SIGNAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><rssi>-51dBm</rssi><rsrp>-95dBm</rsrp><rsrq>-10dB</rsrq><sinr>12dB</sinr></response>"#;
+
+    let output = common::gather(MockModem::new().with("/api/device/signal", SIGNAL)).await;
+
+    for line in [
+        "# TYPE modem_transferred_bytes counter\n",
+        "# TYPE modem_connect_duration_seconds counter\n",
+        "# TYPE modem_session_transferred_bytes gauge\n",
+        "# TYPE modem_session_connect_duration_seconds gauge\n",
+        "# TYPE modem_signal_rssi_dbm gauge\n",
+        "# TYPE modem_signal_rsrp_dbm gauge\n",
+        "# TYPE modem_signal_rsrq_db gauge\n",
+        "# TYPE modem_signal_sinr_db gauge\n",
+    ] {
+        assert!(output.contains(line), "{line}{output}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_times_out_stalled_requests() {
+    let address = MockModem::new()
+        .with_delay("/api/webserver/SesTokInfo", common::SESSION, Duration::from_secs(5))
+        .spawn();
+    let modem = Modem::new(address, Duration::from_millis(300), false).unwrap();
+
+    let start = Instant::now();
+    let output = common::gather_modem(&modem).await;
+    let elapsed = start.elapsed();
+
+    assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+    assert!(output.contains("modem_scrape_success 0\n"), "{output}");
+    assert!(output.contains("modem_up 0\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_html_response_as_session_failure() {
+    const LOGIN_PAGE: &str = "<!DOCTYPE html><html><head><title>Login</title></head><body></body></html>";
+
+    let output = common::gather(MockModem::new().with("/api/monitoring/traffic-statistics", LOGIN_PAGE)).await;
+
+    assert!(output.contains("modem_up 0\n"), "{output}");
+    assert!(output.contains("modem_scrape_success 0\n"), "{output}");
+    assert!(output.contains("modem_scrape_errors_total{kind=\"session\"} 1\n"), "{output}");
+}
+
+#[tokio::test]
+async fn statistics_cache_goes_fresh_stale_expired() {
+    const TTL: Duration = Duration::from_millis(500);
+
+    let cache = StatisticsCache::new(Some(TTL));
+    let modem = Modem::new(MockModem::new().spawn(), Duration::from_secs(5), false).unwrap();
+    let unreachable = Modem::new("http://127.0.0.1:1", Duration::from_secs(5), false).unwrap();
+
+    let fresh = gather_metrics(&modem, &ScrapeMetrics::new(), &cache).await.unwrap();
+    assert!(fresh.contains("modem_up 1\n"), "{fresh}");
+    assert!(fresh.contains("modem_metrics_stale 0\n"), "{fresh}");
+    assert!(fresh.contains("modem_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{fresh}");
+
+    let stale = gather_metrics(&unreachable, &ScrapeMetrics::new(), &cache).await.unwrap();
+    assert!(stale.contains("modem_up 0\n"), "{stale}");
+    assert!(stale.contains("modem_metrics_stale 1\n"), "{stale}");
+    assert!(stale.contains("modem_metrics_age_seconds "), "{stale}");
+    assert!(stale.contains("modem_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{stale}");
+
+    tokio::time::sleep(TTL).await;
+    let expired = gather_metrics(&unreachable, &ScrapeMetrics::new(), &cache).await.unwrap();
+    assert!(expired.contains("modem_up 0\n"), "{expired}");
+    assert!(!expired.contains("modem_metrics_stale"), "{expired}");
+    assert!(!expired.contains("modem_transferred_bytes"), "{expired}");
+}
+
+#[tokio::test]
+async fn gather_metrics_coerces_malformed_traffic_values() {
+    const TRAFFIC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<CurrentConnectTime>abc120</CurrentConnectTime>
+<CurrentUpload>+1000</CurrentUpload>
+<CurrentDownload> 2000 bytes</CurrentDownload>
+<TotalUpload>~30000</TotalUpload>
+<TotalDownload>-40000</TotalDownload>
+<TotalConnectTime>3600s</TotalConnectTime>
+</response>"#;
+
+    let output = common::gather(MockModem::new().with("/api/monitoring/traffic-statistics", TRAFFIC)).await;
+
+    assert!(output.contains("modem_collector_up{collector=\"traffic\"} 1\n"), "{output}");
+    assert!(output.contains("modem_session_connect_duration_seconds 120\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"upload\"} 1000.0\n"), "{output}");
+    assert!(output.contains("modem_session_transferred_bytes{direction=\"download\"} 2000.0\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{output}");
+    assert!(output.contains("modem_transferred_bytes_total{direction=\"download\"} 0\n"), "{output}");
+    assert!(output.contains("modem_connect_duration_seconds_total 3600\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_rejects_partial_traffic_payloads() {
+    const MISSING_FIELDS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><CurrentUpload>1000</CurrentUpload></response>"#;
+    const NON_NUMERIC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<CurrentConnectTime>N/A</CurrentConnectTime>
+<CurrentUpload>1000</CurrentUpload>
+<CurrentDownload>2000</CurrentDownload>
+<TotalUpload>30000</TotalUpload>
+<TotalDownload>40000</TotalDownload>
+<TotalConnectTime>3600</TotalConnectTime>
+</response>"#;
+    const TRUNCATED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><CurrentConnectTime>120</CurrentConnectTime><CurrentUpload>10"#;
+
+    for payload in [MISSING_FIELDS, NON_NUMERIC, TRUNCATED] {
+        let output = common::gather(MockModem::new().with("/api/monitoring/traffic-statistics", payload)).await;
+
+        assert!(output.contains("modem_up 1\n"), "{output}");
+        assert!(output.contains("modem_collector_up{collector=\"traffic\"} 0\n"), "{output}");
+        assert!(output.contains("modem_decode_errors_total{endpoint=\"/api/monitoring/traffic-statistics\"} 1\n"), "{output}");
+        assert!(!output.contains("modem_transferred_bytes"), "{output}");
+    }
+}
+
+#[tokio::test]
+async fn gather_metrics_escapes_label_values() {
+    const PLMN: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><State>0</State><FullName>Op "One" \ Two</FullName><ShortName>One</ShortName><Numeric>00101</Numeric><Rat>7</Rat></response>"#;
+
+    let address = MockModem::new().with("/api/net/current-plmn", PLMN).spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap()
+        .with_labels(vec![("site".to_string(), "a \"quoted\" \\ path\nnext".to_string())]);
+
+    let output = common::gather_modem(&modem).await;
+
+    assert!(output.contains(r#"site="a \"quoted\" \\ path\nnext""#), "{output}");
+    assert!(output.contains(r#"name="Op \"One\" \\ Two""#), "{output}");
+    let families = common::openmetrics::parse(&output).unwrap_or_else(|err| panic!("{err}\n{output}"));
+    assert!(families["modem_up"].samples.iter().all(|sample| sample.contains(r#"site="a \"quoted\" \\ path\nnext""#)));
+}
+
+#[tokio::test]
+async fn gather_metrics_reports_5g_connected() {
+    const CONVERGED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><ConnectionStatus>901</ConnectionStatus><CurrentNetworkType>111</CurrentNetworkType><NrConnected>1</NrConnected><NsaMode>1</NsaMode></response>"#;
+
+    let output = common::gather(MockModem::new().with("/api/monitoring/converged-status", CONVERGED)).await;
+
+    assert!(output.contains("modem_5g_connected 1\n"), "{output}");
+    common::openmetrics::parse(&output).unwrap_or_else(|err| panic!("{err}\n{output}"));
+}
+
+#[tokio::test]
+async fn gather_metrics_tracks_interleaved_traffic_in_arrival_order() {
+    const EARLIER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><CurrentConnectTime>60</CurrentConnectTime><CurrentUpload>500</CurrentUpload><CurrentDownload>1000</CurrentDownload>
+<TotalUpload>20000</TotalUpload><TotalDownload>30000</TotalDownload><TotalConnectTime>3540</TotalConnectTime></response>"#;
+    const SIGNAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response><rssi>-60dBm</rssi></response>"#;
+
+    let address = MockModem::new()
+        .with_first("/api/monitoring/traffic-statistics", EARLIER)
+        .with("/api/device/signal", SIGNAL)
+        .with_first_delay("/api/device/signal", SIGNAL, Duration::from_millis(500))
+        .spawn();
+    let modem = Modem::new(address, Duration::from_secs(5), false).unwrap()
+        .with_lifetime_traffic(Some(LifetimeTraffic::new()));
+    let scrape = ScrapeMetrics::new();
+    let (first_cache, second_cache) = (StatisticsCache::new(None), StatisticsCache::new(None));
+
+    let (first, second) = tokio::join!(
+        gather_metrics(&modem, &scrape, &first_cache),
+        async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            gather_metrics(&modem, &scrape, &second_cache).await
+        },
+    );
+    assert!(first.unwrap().contains("modem_session_connect_duration_seconds 60\n"));
+    assert!(second.unwrap().contains("modem_session_connect_duration_seconds 120\n"));
+
+    let output = gather_metrics(&modem, &scrape, &StatisticsCache::new(None)).await.unwrap();
+    assert!(output.contains("modem_reconnects_total 0\n"), "{output}");
+    assert!(output.contains("modem_lifetime_transferred_bytes_total{direction=\"upload\"} 30000\n"), "{output}");
+    assert!(output.contains("modem_lifetime_transferred_bytes_total{direction=\"download\"} 40000\n"), "{output}");
+}
+
+#[tokio::test]
+async fn gather_metrics_request_duration_includes_body() {
+    let mock = MockModem::new()
+        .with_body_delay("/api/monitoring/traffic-statistics", Duration::from_millis(300));
+
+    let output = common::gather(mock).await;
+
+    let bucket = |le: &str| format!("modem_request_duration_seconds_bucket{{le=\"{le}\",endpoint=\"/api/monitoring/traffic-statistics\"}}");
+    assert!(output.contains(&format!("{} 0\n", bucket("0.25"))), "{output}");
+    assert!(output.contains(&format!("{} 1\n", bucket("0.5"))), "{output}");
+}
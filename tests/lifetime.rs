@@ -0,0 +1,64 @@
+use modem_exporter::modem::{LifetimeTotals, LifetimeTraffic};
+
+fn totals(upload: u64, download: u64) -> LifetimeTotals {
+    LifetimeTotals { upload, download }
+}
+
+#[test]
+fn lifetime_traffic_follows_increasing_totals() {
+    let lifetime = LifetimeTraffic::new();
+
+    assert_eq!(lifetime.totals(), None);
+    assert_eq!(lifetime.observe(totals(100, 200)), totals(100, 200));
+    assert_eq!(lifetime.observe(totals(150, 250)), totals(150, 250));
+}
+
+#[test]
+fn lifetime_traffic_carries_forward_totals_across_resets() {
+    let lifetime = LifetimeTraffic::new();
+
+    lifetime.observe(totals(100, 200));
+    assert_eq!(lifetime.observe(totals(10, 250)), totals(110, 250));
+    assert_eq!(lifetime.observe(totals(30, 20)), totals(130, 270));
+    assert_eq!(lifetime.observe(totals(5, 5)), totals(135, 275));
+    assert_eq!(lifetime.totals(), Some(totals(135, 275)));
+}
+
+#[test]
+fn lifetime_traffic_persists_state() {
+    let path = std::env::temp_dir().join(format!("modem-exporter-lifetime-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let lifetime = LifetimeTraffic::load(&path).unwrap();
+    lifetime.observe(totals(100, 200));
+    lifetime.observe(totals(10, 20));
+    lifetime.save().unwrap();
+
+    let restored = LifetimeTraffic::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(restored.totals(), Some(totals(110, 220)));
+    assert_eq!(restored.observe(totals(5, 30)), totals(115, 230));
+}
+
+#[test]
+fn lifetime_traffic_concurrent_saves_leave_valid_state() {
+    let path = std::env::temp_dir().join(format!("modem-exporter-lifetime-concurrent-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let lifetime = LifetimeTraffic::load(&path).unwrap();
+    std::thread::scope(|scope| {
+        for i in 0..8 {
+            let lifetime = &lifetime;
+            scope.spawn(move || {
+                for j in 0..20 {
+                    lifetime.observe(totals(i * 100 + j, i * 100 + j));
+                    lifetime.save().unwrap();
+                }
+            });
+        }
+    });
+
+    let restored = LifetimeTraffic::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(restored.totals(), lifetime.totals());
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::MockModem;
+use modem_exporter::Credentials;
+use modem_exporter::metrics::DEFAULT_METRIC_PREFIX;
+use modem_exporter::server::{resolve_target, routes, Exporter, IpNetwork, ListenAddress, ModemConfig, DEFAULT_ALLOWED_NETWORKS};
+
+fn default_networks() -> Vec<IpNetwork> {
+    DEFAULT_ALLOWED_NETWORKS.iter().map(|network| network.parse().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn resolve_target_accepts_private_address() {
+    let ip = resolve_target("192.168.8.1", &default_networks()).await.unwrap();
+
+    assert_eq!(ip, IpAddr::V4(Ipv4Addr::new(192, 168, 8, 1)));
+}
+
+#[tokio::test]
+async fn resolve_target_rejects_link_local_metadata_address() {
+    let err = resolve_target("169.254.169.254", &default_networks()).await.unwrap_err();
+
+    assert!(err.to_string().contains("not allowed"), "{err:#}");
+}
+
+#[tokio::test]
+async fn resolve_target_rejects_public_hostname() {
+    assert!(resolve_target("evil.com", &default_networks()).await.is_err());
+}
+
+#[tokio::test]
+async fn resolve_target_rejects_urls() {
+    for target in ["http://192.168.8.1", "192.168.8.1/api", "user@192.168.8.1", "192.168.8.1:8080"] {
+        assert!(resolve_target(target, &default_networks()).await.is_err(), "{target}");
+    }
+}
+
+#[tokio::test]
+async fn resolve_target_uses_configured_networks() {
+    let networks = vec!["169.254.0.0/16".parse().unwrap()];
+
+    assert!(resolve_target("169.254.1.1", &networks).await.is_ok());
+    assert!(resolve_target("192.168.8.1", &networks).await.is_err());
+}
+
+#[test]
+fn listen_address_parses_tcp_and_unix_sockets() {
+    assert_eq!("127.0.0.1:9091".parse::<ListenAddress>().unwrap(), ListenAddress::Tcp(SocketAddr::from(([127, 0, 0, 1], 9091))));
+    assert_eq!(":9091".parse::<ListenAddress>().unwrap(), ListenAddress::Tcp(SocketAddr::from(([0, 0, 0, 0], 9091))));
+    assert_eq!(
+        "unix:/run/modem-exporter.sock".parse::<ListenAddress>().unwrap(),
+        ListenAddress::Unix(PathBuf::from("/run/modem-exporter.sock")),
+    );
+    assert!("unix:".parse::<ListenAddress>().is_err());
+}
+
+#[test]
+fn listen_address_parses_ipv6() {
+    let address = "[::1]:0".parse::<ListenAddress>().unwrap();
+    assert_eq!(address, ListenAddress::Tcp(SocketAddr::from((Ipv6Addr::LOCALHOST, 0))));
+    assert_eq!(address.to_string(), "[::1]:0");
+    assert_eq!("[::]:9091".parse::<ListenAddress>().unwrap(), ListenAddress::Tcp(SocketAddr::from((Ipv6Addr::UNSPECIFIED, 9091))));
+    assert!("::1:9091".parse::<ListenAddress>().is_err());
+
+    let ListenAddress::Tcp(address) = address else { unreachable!() };
+    let listener = std::net::TcpListener::bind(address).unwrap();
+    assert!(listener.local_addr().unwrap().is_ipv6());
+}
+
+fn modem_config(instance_label: &str) -> ModemConfig {
+    ModemConfig {
+        credentials: None,
+        timeout: Duration::from_secs(1),
+        insecure_tls: false,
+        retries: 0,
+        cache_ttl: None,
+        collectors: None,
+        redact_identity: false,
+        metric_prefix: DEFAULT_METRIC_PREFIX.to_string(),
+        instance_label: Some(instance_label.to_string()),
+        instance_alias: None,
+        scrape_deadline: None,
+        state_file: None,
+        user_agent: None,
+        accept_language: None,
+        traffic_path: None,
+    }
+}
+
+#[tokio::test]
+async fn instance_label_matches_for_configured_and_resolved_address() {
+    let config = modem_config("instance");
+    let configured = config.build("http://127.0.0.1/").unwrap();
+    let resolved = config.build(resolve_target("127.0.0.1", &["127.0.0.0/8".parse().unwrap()]).await.unwrap().to_string()).unwrap();
+
+    for modem in [configured, resolved] {
+        let output = common::gather_modem(&modem).await;
+        assert!(output.contains("modem_up{instance=\"127.0.0.1\"} 0\n"), "{output}");
+    }
+}
+
+#[tokio::test]
+async fn instance_label_uses_host_or_alias() {
+    let config = modem_config("modem");
+    for (address, host) in [("http://127.0.0.1:1/", "127.0.0.1:1"), ("https://[::1]:1/prefix", "[::1]:1")] {
+        let output = common::gather_modem(&config.build(address).unwrap()).await;
+        assert!(output.contains(&format!("modem_up{{modem=\"{host}\"}} 0\n")), "{output}");
+    }
+
+    let config = ModemConfig { instance_alias: Some("office".to_string()), ..modem_config("modem") };
+    let output = common::gather_modem(&config.build("http://127.0.0.1:1").unwrap()).await;
+    assert!(output.contains("modem_up{modem=\"office\"} 0\n"), "{output}");
+}
+
+#[tokio::test]
+async fn configured_targets_receive_credentials() {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+
+    let default = MockModem::new().with("/api/user/login", OK);
+    let configured = MockModem::new().with("/api/user/login", OK);
+    let (default_requests, configured_requests) = (default.requests(), configured.requests());
+    let configured = configured.spawn();
+    let config = ModemConfig {
+        credentials: Some(Credentials { username: "admin".to_string(), password: "secret".to_string() }),
+        ..modem_config("instance")
+    };
+    let exporter = Exporter::new(default.spawn(), config, Some(vec![configured.clone()])).unwrap();
+    let routes = routes(Arc::new(exporter));
+
+    for path in ["/metrics".to_string(), format!("/metrics?target={configured}")] {
+        let response = warp::test::request().path(&path).reply(&routes).await;
+        assert_eq!(response.status(), 200, "{path}");
+    }
+
+    for requests in [default_requests, configured_requests] {
+        assert_eq!(requests.lock().unwrap().iter().filter(|(path, _)| path == "/api/user/login").count(), 1);
+    }
+}
+
+async fn admin_status(admin_token: Option<&str>, path: &str, authorization: Option<&str>, body: &str) -> u16 {
+    const OK: &str = r#"<?xml version="1.0" encoding="UTF-8"?><response>OK</response>"#;
+
+    let address = MockModem::new().with("/api/net/net-mode", OK).with("/api/sms/send-sms", OK).spawn();
+    let exporter = Exporter::new(address, modem_config("instance"), None).unwrap()
+        .with_admin_token(admin_token.map(str::to_string));
+    let mut request = warp::test::request().method("POST").path(path).body(body);
+    if let Some(authorization) = authorization {
+        request = request.header("authorization", authorization);
+    }
+    request.reply(&routes(Arc::new(exporter))).await.status().as_u16()
+}
+
+#[tokio::test]
+async fn admin_net_mode_checks_token_before_body() {
+    const PATH: &str = "/admin/net-mode";
+    const BODY: &str = r#"{"NetworkMode":"03","NetworkBand":"3FFFFFFF","LTEBand":"7FFFFFFFFFFFFFFF"}"#;
+    let oversized = format!(r#"{{"NetworkMode":"{}"}}"#, "0".repeat(5000));
+
+    assert_eq!(admin_status(None, PATH, None, "{").await, 404);
+    assert_eq!(admin_status(Some("secret"), PATH, None, "{").await, 401);
+    assert_eq!(admin_status(Some("secret"), PATH, Some("Bearer wrong"), "{").await, 401);
+    assert_eq!(admin_status(Some("secret"), PATH, Some("Bearer secret"), "{").await, 400);
+    assert_eq!(admin_status(Some("secret"), PATH, Some("Bearer secret"), &oversized).await, 413);
+    assert_eq!(admin_status(Some("secret"), PATH, Some("Bearer secret"), BODY).await, 200);
+}
+
+#[tokio::test]
+async fn admin_send_sms_checks_token_before_body() {
+    const PATH: &str = "/admin/send-sms";
+    const BODY: &str = r#"{"number":"+15550100","text":"hello"}"#;
+    let oversized = format!(r#"{{"number":"+15550100","text":"{}"}}"#, "a".repeat(5000));
+
+    assert_eq!(admin_status(None, PATH, None, "{").await, 404);
+    assert_eq!(admin_status(Some("secret"), PATH, None, "{").await, 401);
+    assert_eq!(admin_status(Some("secret"), PATH, Some("Bearer secret"), "{").await, 400);
+    assert_eq!(admin_status(Some("secret"), PATH, Some("Bearer secret"), &oversized).await, 413);
+    assert_eq!(admin_status(Some("secret"), PATH, Some("Bearer secret"), BODY).await, 200);
+}
+
+#[tokio::test]
+async fn metrics_negotiates_content_type() {
+    const PROMETHEUS_TEXT: &str = "application/openmetrics-text;version=1.0.0,application/openmetrics-text;version=0.0.1;q=0.75,text/plain;version=0.0.4;q=0.5,*/*;q=0.1";
+    const PROMETHEUS_PROTOBUF: &str = "application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;encoding=delimited;q=0.7,text/plain;version=0.0.4;q=0.3,*/*;q=0.2";
+    const OPENMETRICS: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+    const TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+    const PROTOBUF: &str = "application/vnd.google.protobuf; proto=io.prometheus.client.MetricFamily; encoding=delimited";
+
+    let exporter = Exporter::new(MockModem::new().spawn(), modem_config("instance"), None).unwrap();
+    let routes = routes(Arc::new(exporter));
+    let cases = [
+        (None, TEXT),
+        (Some("*/*"), TEXT),
+        (Some("text/html"), TEXT),
+        (Some("application/openmetrics-text"), OPENMETRICS),
+        (Some(PROMETHEUS_TEXT), OPENMETRICS),
+        (Some("text/plain;q=0.9,application/openmetrics-text;q=0.5"), TEXT),
+        (Some(PROMETHEUS_PROTOBUF), PROTOBUF),
+        (Some("application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;encoding=text,*/*;q=0.1"), TEXT),
+    ];
+    for (accept, content_type) in cases {
+        let mut request = warp::test::request().path("/metrics");
+        if let Some(accept) = accept {
+            request = request.header("accept", accept);
+        }
+        let response = request.reply(&routes).await;
+        assert_eq!(response.status(), 200, "{accept:?}");
+        assert_eq!(response.headers()["content-type"], content_type, "{accept:?}");
+    }
+}
+
+#[tokio::test]
+async fn metrics_encodes_protobuf() {
+    const ACCEPT: &str = "application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;encoding=delimited";
+
+    let address = MockModem::new().spawn();
+    let host = address.trim_start_matches("http://").to_string();
+    let exporter = Exporter::new(address, modem_config("instance"), None).unwrap();
+    let response = warp::test::request().path("/metrics").header("accept", ACCEPT)
+        .reply(&routes(Arc::new(exporter))).await;
+    let families = common::protobuf::parse(response.body()).unwrap();
+
+    let up = &families["modem_up"];
+    assert_eq!(up.metric_type, 1);
+    assert_eq!(up.help, "Whether the modem API was reachable.");
+    assert_eq!(common::protobuf::sample(up.metrics[0]), (vec![("instance".to_string(), host)], 1.0));
+
+    let transferred = &families["modem_transferred_bytes_total"];
+    assert_eq!(transferred.metric_type, 0);
+    let samples = transferred.metrics.iter().map(|metric| common::protobuf::sample(metric)).collect::<Vec<_>>();
+    assert!(samples.iter().any(|(labels, value)| labels.contains(&("direction".to_string(), "upload".to_string())) && *value == 30000.0), "{samples:?}");
+
+    let duration = &families["modem_request_duration_seconds"];
+    assert_eq!(duration.metric_type, 4);
+    assert!(!duration.metrics.is_empty());
+    assert!(!families.contains_key("modem_transferred_bytes"));
+}